@@ -11,9 +11,9 @@ use rayon::prelude::*;
 use serde::Deserialize;
 use std::f32;
 
-/// An example application that shows opening an HDR EXR image with optional
-/// additional normal and albedo EXR images and denoising it with OIDN.
-/// The denoised image is then tonemaped and saved out as a JPG
+// An example application that shows opening an HDR EXR image with optional
+// additional normal and albedo EXR images and denoising it with OIDN.
+// The denoised image is then tonemaped and saved out as a JPG
 
 const USAGE: &str = "
 denoise_exr
@@ -126,18 +126,16 @@ fn main() {
 
         if let Some(normal_exr) = args.flag_n.clone() {
             normal = load_exr(&normal_exr);
-            denoiser.albedo_normal(&albedo.img[..], &normal.img[..]);
+            denoiser
+                .albedo_normal(&albedo.img[..], &normal.img[..])
+                .unwrap();
         } else {
-            denoiser.albedo(&albedo.img[..]);
+            denoiser.albedo(&albedo.img[..]).unwrap();
         }
     }
 
-    denoiser
-        .filter_in_place(&mut color.img[..])
-        .expect("Invalid input image dimensions?");
-
-    if let Err(e) = device.get_error() {
-        println!("Error denosing image: {}", e.1);
+    if let Err(e) = denoiser.filter_in_place(&mut color.img[..]) {
+        println!("Error denosing image: {e}");
     }
 
     let exposure = 2.0_f32.powf(args.flag_e);
@@ -3,8 +3,8 @@ extern crate oidn;
 
 use std::env;
 
-/// A simple test application that shows opening a color image and passing
-/// it to OIDN for denoising. The denoised image is then saved out.
+// A simple test application that shows opening a color image and passing
+// it to OIDN for denoising. The denoised image is then saved out.
 
 fn main() {
     let args: Vec<_> = env::args().collect();
@@ -32,12 +32,8 @@ fn main() {
     filter
         .srgb(true)
         .image_dimensions(input.width() as usize, input.height() as usize);
-    filter
-        .filter(&input_img[..], &mut filter_output[..])
-        .expect("Invalid input image dimensions?");
-
-    if let Err(e) = device.get_error() {
-        println!("Error denosing image: {}", e.1);
+    if let Err(e) = filter.filter(&input_img[..], &mut filter_output[..]) {
+        println!("Error denosing image: {e}");
     }
 
     let mut output_img = vec![0u8; filter_output.len()];
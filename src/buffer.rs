@@ -1,70 +1,578 @@
+use std::{
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    ptr::{self, NonNull},
+    slice,
+};
+
 use crate::sys::{
-    oidnGetBufferSize, oidnNewBuffer, oidnReleaseBuffer, oidnWriteBuffer, OIDNBuffer,
+    oidnGetBufferData, oidnGetBufferSize, oidnGetBufferStorage, oidnNewBuffer,
+    oidnNewBufferWithStorage, oidnNewSharedBuffer, oidnReadBuffer, oidnReadBufferAsync,
+    oidnReleaseBuffer, oidnWriteBuffer, oidnWriteBufferAsync, OIDNBuffer,
 };
-use crate::Device;
+use crate::{
+    device::Pending,
+    error::{OidnError, Operation},
+    Device, Error, Format, Storage,
+};
+#[cfg(unix)]
+use crate::{sys::oidnNewSharedBufferFromFD, ExternalMemoryType};
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, IntoRawFd, OwnedFd};
+
+mod private {
+    pub trait Sealed {}
+
+    /// Gives the crate mutable access to the buffer of an
+    /// [super::OutputBuffer] without handing it out to users
+    pub trait AsBufferMut<T: super::BufferElement> {
+        fn as_buffer_mut(&mut self) -> &mut super::Buffer<T>;
+    }
+}
+
+/// Types which can be stored in a [Buffer] and read from and written to host
+/// memory.
+///
+/// Half precision floats are supported with the `half` feature, images stored
+/// as `half::f16` must use one of the `Format::Half*` formats. Byte buffers can
+/// hold images of any format.
+pub trait BufferElement: Copy + Default + private::Sealed + 'static {
+    /// The image formats which can be stored in a buffer of this type
+    const FORMATS: &'static [Format];
+}
+
+impl private::Sealed for f32 {}
+impl BufferElement for f32 {
+    const FORMATS: &'static [Format] = &[
+        Format::Float,
+        Format::Float2,
+        Format::Float3,
+        Format::Float4,
+    ];
+}
+
+#[cfg(feature = "half")]
+impl private::Sealed for half::f16 {}
+#[cfg(feature = "half")]
+impl BufferElement for half::f16 {
+    const FORMATS: &'static [Format] = &[Format::Half, Format::Half2, Format::Half3, Format::Half4];
+}
+
+impl private::Sealed for u8 {}
+impl BufferElement for u8 {
+    const FORMATS: &'static [Format] = &[
+        Format::Float,
+        Format::Float2,
+        Format::Float3,
+        Format::Float4,
+        Format::Half,
+        Format::Half2,
+        Format::Half3,
+        Format::Half4,
+    ];
+}
+
+/// Reinterprets a slice of buffer elements as bytes
+pub(crate) fn as_bytes<T: BufferElement>(contents: &[T]) -> &[u8] {
+    // All buffer elements are plain data without padding.
+    unsafe { slice::from_raw_parts(contents.as_ptr() as *const u8, mem::size_of_val(contents)) }
+}
 
-pub struct Buffer {
+/// A buffer of elements of type `T` allocated by a [Device].
+///
+/// The buffer tracks both its size in bytes and the number of elements it
+/// holds, so it can't be mixed up with buffers of a different element type.
+pub struct Buffer<T: BufferElement = f32> {
     pub(crate) buf: OIDNBuffer,
     pub(crate) size: usize,
-    pub(crate) id: isize,
+    pub(crate) byte_size: usize,
+    /// The device which created the buffer, kept alive by the buffer
+    pub(crate) device: Device,
+    _element: PhantomData<T>,
 }
 
 impl Device {
-    /// Creates a new buffer from a slice, returns null if buffer creation failed
-    pub fn create_buffer(&self, contents: &[f32]) -> Option<Buffer> {
-        let byte_size = std::mem::size_of_val(contents);
-        let buffer = unsafe {
-            let buf = oidnNewBuffer(self.0, byte_size);
-            if buf.is_null() {
-                return None;
-            }
-            oidnWriteBuffer(buf, 0, byte_size, contents.as_ptr() as *const _);
-            buf
+    /// Creates a new buffer from a slice
+    pub fn create_buffer<T: BufferElement>(&self, contents: &[T]) -> Result<Buffer<T>, OidnError> {
+        let buf = unsafe { oidnNewBuffer(self.handle, mem::size_of_val(contents)) };
+        self.init_buffer(buf, contents)
+    }
+    /// Creates a new buffer from a slice in the given storage, which fails if
+    /// the storage is not supported by the device.
+    ///
+    /// Buffers in [Storage::Host] or [Storage::Managed] can be accessed
+    /// directly with [Buffer::as_slice] and [Buffer::as_mut_slice].
+    pub fn create_buffer_with_storage<T: BufferElement>(
+        &self,
+        contents: &[T],
+        storage: Storage,
+    ) -> Result<Buffer<T>, OidnError> {
+        let buf = unsafe {
+            oidnNewBufferWithStorage(
+                self.handle,
+                mem::size_of_val(contents),
+                storage.as_raw_oidn_storage(),
+            )
         };
-        Some(Buffer {
-            buf: buffer,
-            size: contents.len(),
-            id: self.0 as isize,
+        self.init_buffer(buf, contents)
+    }
+    /// Creates a buffer of `len` elements without initializing its contents
+    pub(crate) fn allocate_buffer<T: BufferElement>(
+        &self,
+        len: usize,
+    ) -> Result<Buffer<T>, OidnError> {
+        let byte_size = len * mem::size_of::<T>();
+        let buf = unsafe { oidnNewBuffer(self.handle, byte_size) };
+        self.wrap_new_buffer(buf, len)
+    }
+    /// Creates a buffer using the memory of a slice instead of allocating new
+    /// memory.
+    ///
+    /// Changes to the buffer are made directly to the slice, which stays
+    /// borrowed for as long as the buffer exists. The device must be able to
+    /// access host memory (see [crate::DeviceInfo::system_memory_supported]),
+    /// which is always the case for the CPU device.
+    pub fn shared_buffer<'a, T: BufferElement>(
+        &self,
+        memory: &'a mut [T],
+    ) -> Result<SharedBuffer<'a, T>, OidnError> {
+        let buffer = unsafe { self.shared_buffer_from_raw(memory.as_mut_ptr(), memory.len())? };
+        Ok(SharedBuffer {
+            buffer,
+            _memory: PhantomData,
+        })
+    }
+    /// Creates a buffer using the memory at `data`
+    ///
+    /// # Safety
+    /// `data` must be valid for `len` elements for as long as the buffer
+    /// exists, and must not be accessed elsewhere while the device writes to it
+    pub(crate) unsafe fn shared_buffer_from_raw<T: BufferElement>(
+        &self,
+        data: *mut T,
+        len: usize,
+    ) -> Result<Buffer<T>, OidnError> {
+        let byte_size = len * mem::size_of::<T>();
+        let buf = oidnNewSharedBuffer(self.handle, data as *mut _, byte_size);
+        if buf.is_null() {
+            return Err(self.operation_error(
+                Operation::BufferCreation,
+                Error::Unknown,
+                "failed to share the memory with the device",
+            ));
+        }
+        Ok(Buffer {
+            buf,
+            size: len,
+            byte_size,
+            device: self.clone(),
+            _element: PhantomData,
         })
     }
+    /// Wraps a newly created buffer and writes the contents to it
+    fn init_buffer<T: BufferElement>(
+        &self,
+        buf: OIDNBuffer,
+        contents: &[T],
+    ) -> Result<Buffer<T>, OidnError> {
+        let buffer = self.wrap_new_buffer(buf, contents.len())?;
+        unsafe {
+            oidnWriteBuffer(buf, 0, buffer.byte_size, contents.as_ptr() as *const _);
+        }
+        Ok(buffer)
+    }
+    /// Wraps a newly created buffer of `len` elements, fails if creating it
+    /// failed
+    fn wrap_new_buffer<T: BufferElement>(
+        &self,
+        buf: OIDNBuffer,
+        len: usize,
+    ) -> Result<Buffer<T>, OidnError> {
+        if buf.is_null() {
+            return Err(self.operation_error(
+                Operation::BufferCreation,
+                Error::OutOfMemory,
+                "failed to allocate the buffer",
+            ));
+        }
+        Ok(Buffer {
+            buf,
+            size: len,
+            byte_size: len * mem::size_of::<T>(),
+            device: self.clone(),
+            _element: PhantomData,
+        })
+    }
+    /// Wraps a raw buffer, any trailing bytes which do not make up a whole
+    /// element are not counted in the number of elements.
+    ///
     /// # Safety
     /// Raw buffer must not be invalid (e.g. destroyed, null ect.)
     ///
     /// Raw buffer must have been created by this device
-    pub unsafe fn create_buffer_from_raw(&self, buffer: OIDNBuffer) -> Buffer {
-        let size = oidnGetBufferSize(buffer);
+    pub unsafe fn create_buffer_from_raw<T: BufferElement>(&self, buffer: OIDNBuffer) -> Buffer<T> {
+        let byte_size = oidnGetBufferSize(buffer);
         Buffer {
             buf: buffer,
-            size,
-            id: self.0 as isize,
+            size: byte_size / mem::size_of::<T>(),
+            byte_size,
+            device: self.clone(),
+            _element: PhantomData,
         }
     }
 }
 
-impl Buffer {
-    /// Writes to the buffer, returns [None] if the sizes mismatch
-    pub fn write(&mut self, contents: &[f32]) -> Option<()> {
-        if self.size != contents.len() {
-            return None;
+#[cfg(unix)]
+impl Device {
+    /// Imports `byte_size` bytes of external memory (e.g. exported by Vulkan)
+    /// from a POSIX file descriptor as a buffer.
+    ///
+    /// The memory type must be in [crate::DeviceInfo::external_memory_types],
+    /// otherwise an [Error::UnsupportedHardware] error is returned without
+    /// calling into Open Image Denoise. On success the buffer takes ownership
+    /// of the file descriptor, on failure it is closed.
+    pub fn import_fd<T: BufferElement>(
+        &self,
+        memory_type: ExternalMemoryType,
+        fd: OwnedFd,
+        byte_size: usize,
+    ) -> Result<Buffer<T>, OidnError> {
+        let supported = self.info().external_memory_types;
+        if !supported.contains(memory_type.into()) {
+            return Err(OidnError::new(
+                Error::UnsupportedHardware,
+                Operation::BufferCreation,
+                format!("{memory_type:?} memory is not supported by the device"),
+            ));
         }
-        let byte_size = std::mem::size_of_val(contents);
+        let buf = unsafe {
+            oidnNewSharedBufferFromFD(
+                self.handle,
+                memory_type.as_raw_oidn_external_memory_type(),
+                fd.as_raw_fd(),
+                byte_size,
+            )
+        };
+        if buf.is_null() {
+            return Err(self.operation_error(
+                Operation::BufferCreation,
+                Error::Unknown,
+                "failed to import external memory",
+            ));
+        }
+        // The buffer owns the file descriptor now.
+        let _ = fd.into_raw_fd();
+        Ok(unsafe { self.create_buffer_from_raw(buf) })
+    }
+}
+
+impl<T: BufferElement> Buffer<T> {
+    /// Writes to the buffer, fails if the sizes mismatch
+    pub fn write(&mut self, contents: &[T]) -> Result<(), OidnError> {
+        self.check_size(contents.len())?;
+        self.write_at(0, contents)
+    }
+    /// Writes to the buffer starting at the element `offset`, fails if the
+    /// contents do not fit in the buffer
+    pub fn write_at(&mut self, offset: usize, contents: &[T]) -> Result<(), OidnError> {
+        let byte_offset = self.byte_range(offset, contents.len())?;
         unsafe {
-            oidnWriteBuffer(self.buf, 0, byte_size, contents.as_ptr() as *const _);
+            oidnWriteBuffer(
+                self.buf,
+                byte_offset,
+                mem::size_of_val(contents),
+                contents.as_ptr() as *const _,
+            );
         }
-        Some(())
+        Ok(())
+    }
+    /// Starts writing to the buffer asynchronously, the same as
+    /// [Buffer::write] otherwise.
+    ///
+    /// The buffer and contents stay borrowed until the returned [Pending] is
+    /// waited on or dropped.
+    ///
+    /// # Safety
+    /// The returned [Pending] must not be leaked (e.g. with [mem::forget]),
+    /// otherwise the contents could be freed or changed while the device still
+    /// reads them.
+    pub unsafe fn write_async<'a>(
+        &'a mut self,
+        contents: &'a [T],
+    ) -> Result<Pending<'a>, OidnError> {
+        self.check_size(contents.len())?;
+        self.write_at_async(0, contents)
+    }
+    /// Starts writing to the buffer asynchronously, the same as
+    /// [Buffer::write_at] otherwise.
+    ///
+    /// The buffer and contents stay borrowed until the returned [Pending] is
+    /// waited on or dropped.
+    ///
+    /// # Safety
+    /// See [Buffer::write_async].
+    pub unsafe fn write_at_async<'a>(
+        &'a mut self,
+        offset: usize,
+        contents: &'a [T],
+    ) -> Result<Pending<'a>, OidnError> {
+        let byte_offset = self.byte_range(offset, contents.len())?;
+        oidnWriteBufferAsync(
+            self.buf,
+            byte_offset,
+            mem::size_of_val(contents),
+            contents.as_ptr() as *const _,
+        );
+        Ok(Pending::new(self.device.handle))
+    }
+    /// Reads the whole buffer, fails if the sizes mismatch
+    pub fn read(&self, out: &mut [T]) -> Result<(), OidnError> {
+        self.check_size(out.len())?;
+        self.read_at(0, out)
+    }
+    /// Reads from the buffer starting at the element `offset`, fails if the
+    /// range read is out of bounds
+    pub fn read_at(&self, offset: usize, out: &mut [T]) -> Result<(), OidnError> {
+        let byte_offset = self.byte_range(offset, out.len())?;
+        unsafe {
+            oidnReadBuffer(
+                self.buf,
+                byte_offset,
+                mem::size_of_val(out),
+                out.as_mut_ptr() as *mut _,
+            );
+        }
+        Ok(())
+    }
+    /// Starts reading the whole buffer asynchronously, the same as
+    /// [Buffer::read] otherwise.
+    ///
+    /// The buffer and output stay borrowed until the returned [Pending] is
+    /// waited on or dropped, only then is the output filled.
+    ///
+    /// # Safety
+    /// The returned [Pending] must not be leaked (e.g. with [mem::forget]),
+    /// otherwise the output could be freed or accessed while the device still
+    /// writes to it.
+    pub unsafe fn read_async<'a>(&'a self, out: &'a mut [T]) -> Result<Pending<'a>, OidnError> {
+        self.check_size(out.len())?;
+        self.read_at_async(0, out)
+    }
+    /// Starts reading from the buffer asynchronously, the same as
+    /// [Buffer::read_at] otherwise.
+    ///
+    /// The buffer and output stay borrowed until the returned [Pending] is
+    /// waited on or dropped, only then is the output filled.
+    ///
+    /// # Safety
+    /// See [Buffer::read_async].
+    pub unsafe fn read_at_async<'a>(
+        &'a self,
+        offset: usize,
+        out: &'a mut [T],
+    ) -> Result<Pending<'a>, OidnError> {
+        let byte_offset = self.byte_range(offset, out.len())?;
+        oidnReadBufferAsync(
+            self.buf,
+            byte_offset,
+            mem::size_of_val(out),
+            out.as_mut_ptr() as *mut _,
+        );
+        Ok(Pending::new(self.device.handle))
+    }
+    /// Reads the whole buffer into a new vector
+    pub fn to_vec(&self) -> Vec<T> {
+        let mut out = vec![T::default(); self.size];
+        unsafe {
+            oidnReadBuffer(
+                self.buf,
+                0,
+                mem::size_of_val(out.as_slice()),
+                out.as_mut_ptr() as *mut _,
+            );
+        }
+        out
+    }
+    /// Checks `len` elements are the whole buffer
+    fn check_size(&self, len: usize) -> Result<(), OidnError> {
+        if len != self.size {
+            return Err(OidnError::new(
+                Error::InvalidArgument,
+                Operation::BufferAccess,
+                format!("expected {} elements but got {len}", self.size),
+            ));
+        }
+        Ok(())
+    }
+    /// Returns the byte offset of `len` elements starting at the element
+    /// `offset`, fails if they are out of bounds
+    fn byte_range(&self, offset: usize, len: usize) -> Result<usize, OidnError> {
+        if offset.checked_add(len).is_none_or(|end| end > self.size) {
+            return Err(OidnError::new(
+                Error::InvalidArgument,
+                Operation::BufferAccess,
+                format!(
+                    "{len} elements at offset {offset} are out of bounds of {} elements",
+                    self.size
+                ),
+            ));
+        }
+        Ok(offset * mem::size_of::<T>())
+    }
+    /// Where the memory of the buffer is stored, [None] if the storage is not
+    /// known to this version of the bindings
+    pub fn storage(&self) -> Option<Storage> {
+        unsafe { oidnGetBufferStorage(self.buf) }.try_into().ok()
+    }
+    /// Accesses the contents of the buffer directly without copying, returns
+    /// [None] if the buffer is not accessible by the host (see
+    /// [Storage::host_accessible]).
+    pub fn as_slice(&self) -> Option<&[T]> {
+        let data = self.host_data()?;
+        Some(unsafe { slice::from_raw_parts(data as *const T, self.size) })
+    }
+    /// Mutably accesses the contents of the buffer directly without copying,
+    /// returns [None] if the buffer is not accessible by the host (see
+    /// [Storage::host_accessible]).
+    pub fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        let data = self.host_data()?;
+        Some(unsafe { slice::from_raw_parts_mut(data, self.size) })
+    }
+    /// The pointer to the contents of the buffer if it is host accessible
+    fn host_data(&self) -> Option<*mut T> {
+        if !self.storage()?.host_accessible() {
+            return None;
+        }
+        let data = unsafe { oidnGetBufferData(self.buf) } as *mut T;
+        if self.size == 0 {
+            return Some(NonNull::dangling().as_ptr());
+        }
+        (!data.is_null()).then_some(data)
     }
     /// # Safety
     /// Raw buffer must not be made invalid (e.g. by destroying it)
     pub unsafe fn raw(&self) -> OIDNBuffer {
         self.buf
     }
+    /// The number of elements in the buffer
     pub fn size(&self) -> usize {
         self.size
     }
+    /// The size of the buffer in bytes
+    pub fn byte_size(&self) -> usize {
+        self.byte_size
+    }
+    /// Reinterprets a buffer of bytes as a buffer of `T`, the inverse of
+    /// [Buffer::into_bytes]
+    pub(crate) fn from_bytes(buffer: Buffer<u8>) -> Self {
+        let buffer = mem::ManuallyDrop::new(buffer);
+        // The buffer is not dropped, so its device can be moved out.
+        let device = unsafe { ptr::read(&buffer.device) };
+        Buffer {
+            buf: buffer.buf,
+            size: buffer.byte_size / mem::size_of::<T>(),
+            byte_size: buffer.byte_size,
+            device,
+            _element: PhantomData,
+        }
+    }
+    /// Reinterprets the buffer as a buffer of bytes
+    pub fn into_bytes(self) -> Buffer<u8> {
+        let buffer = mem::ManuallyDrop::new(self);
+        // The buffer is not dropped, so its device can be moved out.
+        let device = unsafe { ptr::read(&buffer.device) };
+        Buffer {
+            buf: buffer.buf,
+            size: buffer.byte_size,
+            byte_size: buffer.byte_size,
+            device,
+            _element: PhantomData,
+        }
+    }
 }
 
-impl Drop for Buffer {
+impl<T: BufferElement> Drop for Buffer<T> {
     fn drop(&mut self) {
         unsafe { oidnReleaseBuffer(self.buf) }
     }
 }
+
+unsafe impl<T: BufferElement> Send for Buffer<T> {}
+
+/// A buffer which a filter can write its output to, either a [Buffer] or a
+/// [SharedBuffer].
+pub trait OutputBuffer<T: BufferElement>: private::AsBufferMut<T> {}
+
+impl<T: BufferElement> private::AsBufferMut<T> for Buffer<T> {
+    fn as_buffer_mut(&mut self) -> &mut Buffer<T> {
+        self
+    }
+}
+impl<T: BufferElement> OutputBuffer<T> for Buffer<T> {}
+
+/// The buffer a filter writes its output to
+pub(crate) fn output_buffer<T: BufferElement>(output: &mut impl OutputBuffer<T>) -> &mut Buffer<T> {
+    output.as_buffer_mut()
+}
+
+/// A [Buffer] using memory borrowed from a slice, created by
+/// [Device::shared_buffer].
+///
+/// Only shared access to the buffer is given out, so it can't be swapped with
+/// a buffer outliving the slice, e.g. to use it as the input of a filter
+/// without copying. Filters can still write to it as an [OutputBuffer].
+pub struct SharedBuffer<'a, T: BufferElement = f32> {
+    buffer: Buffer<T>,
+    _memory: PhantomData<&'a mut [T]>,
+}
+
+impl<'a, T: BufferElement> Deref for SharedBuffer<'a, T> {
+    type Target = Buffer<T>;
+
+    fn deref(&self) -> &Buffer<T> {
+        &self.buffer
+    }
+}
+
+impl<'a, T: BufferElement> private::AsBufferMut<T> for SharedBuffer<'a, T> {
+    fn as_buffer_mut(&mut self) -> &mut Buffer<T> {
+        &mut self.buffer
+    }
+}
+impl<'a, T: BufferElement> OutputBuffer<T> for SharedBuffer<'a, T> {}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::{
+        ffi::{c_char, c_int, c_uint},
+        fs::File,
+        os::fd::{FromRawFd, OwnedFd},
+    };
+
+    use crate::{Device, Error, ExternalMemoryType, Operation};
+
+    extern "C" {
+        fn memfd_create(name: *const c_char, flags: c_uint) -> c_int;
+    }
+
+    fn memfd(byte_size: u64) -> OwnedFd {
+        let fd = unsafe { memfd_create(c"oidn-test".as_ptr(), 0) };
+        assert!(fd >= 0, "memfd_create failed");
+        let file = unsafe { File::from_raw_fd(fd) };
+        file.set_len(byte_size).unwrap();
+        file.into()
+    }
+
+    #[test]
+    fn cpu_device_does_not_import_fd() {
+        let device = Device::cpu();
+        for memory_type in [ExternalMemoryType::OpaqueFd, ExternalMemoryType::DmaBuf] {
+            let Err(error) = device.import_fd::<f32>(memory_type, memfd(64), 64) else {
+                panic!("the CPU device imported {memory_type:?} memory");
+            };
+            assert_eq!(error.code, Error::UnsupportedHardware);
+            assert_eq!(error.operation, Operation::BufferCreation);
+        }
+    }
+}
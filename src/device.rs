@@ -1,76 +1,147 @@
-use std::{ffi::CStr, os::raw::c_char, ptr};
+use std::{
+    ffi::CStr,
+    marker::PhantomData,
+    os::raw::{c_char, c_void},
+    ptr,
+    sync::{Arc, RwLock},
+};
 
+use crate::error::{OidnError, Operation};
+use crate::physical_device::{PciAddress, PhysicalDevice};
 use crate::sys::*;
-use crate::Error;
+use crate::{DeviceType, Error, ExternalMemoryTypeFlags};
 
 /// An Open Image Denoise device (e.g. a CPU).
 ///
 /// Open Image Denoise supports a device concept, which allows different
 /// components of the application to use the API without interfering with each
 /// other.
-pub struct Device(pub(crate) OIDNDevice);
+///
+/// Cloning a device is cheap, the clones refer to the same underlying device
+/// which is released once all clones (and the filters and buffers created with
+/// it) are dropped. The device can be shared across threads, Open Image Denoise
+/// serializes operations using the same device so the number of calls from
+/// different threads should be kept low.
+pub struct Device {
+    pub(crate) handle: OIDNDevice,
+    // Shared by all clones, only [None] while dropping.
+    error_handler: Option<Arc<RwLock<Option<ErrorHandler>>>>,
+}
+
+type ErrorHandler = Box<dyn Fn(Error, &str) + Send + Sync>;
 
 impl Device {
     /// Create a device using the fastest device available to run denoising
+    ///
+    /// # Panics
+    /// - if the device could not be created, see [Device::try_new]
     pub fn new() -> Self {
-        let handle = unsafe { oidnNewDevice(OIDNDeviceType_OIDN_DEVICE_TYPE_DEFAULT) };
-        unsafe {
-            oidnCommitDevice(handle);
-        }
-        Self(handle)
+        Self::try_new(DeviceType::Default)
+            .unwrap_or_else(|e| panic!("Failed to create default device: {}", e.message))
     }
 
     /// Create a device to run denoising on the CPU
+    ///
+    /// # Panics
+    /// - if the device could not be created, see [Device::try_new]
     pub fn cpu() -> Self {
-        let handle = unsafe { oidnNewDevice(OIDNDeviceType_OIDN_DEVICE_TYPE_CPU) };
-        unsafe {
-            oidnCommitDevice(handle);
-        }
-        Self(handle)
+        Self::try_new(DeviceType::Cpu)
+            .unwrap_or_else(|e| panic!("Failed to create CPU device: {}", e.message))
     }
 
+    /// Create a device to run denoising on a CUDA GPU, returns [None] if the
+    /// device could not be created. Use [Device::try_new] to get the reason.
     pub fn cuda() -> Option<Self> {
-        let handle = unsafe { oidnNewDevice(OIDNDeviceType_OIDN_DEVICE_TYPE_CUDA) };
-        if handle.is_null() {
-            return None;
-        }
-        unsafe {
-            oidnCommitDevice(handle);
-        }
-        Some(Self(handle))
+        Self::try_new(DeviceType::Cuda).ok()
     }
 
+    /// Create a device to run denoising on a SYCL GPU, returns [None] if the
+    /// device could not be created. Use [Device::try_new] to get the reason.
     pub fn sycl() -> Option<Self> {
-        let handle = unsafe { oidnNewDevice(OIDNDeviceType_OIDN_DEVICE_TYPE_SYCL) };
-        if handle.is_null() {
-            return None;
-        }
-        unsafe {
-            oidnCommitDevice(handle);
-        }
-        Some(Self(handle))
+        Self::try_new(DeviceType::Sycl).ok()
     }
 
+    /// Create a device to run denoising on a HIP GPU, returns [None] if the
+    /// device could not be created. Use [Device::try_new] to get the reason.
     pub fn hip() -> Option<Self> {
-        let handle = unsafe { oidnNewDevice(OIDNDeviceType_OIDN_DEVICE_TYPE_HIP) };
-        if handle.is_null() {
-            return None;
-        }
-        unsafe {
-            oidnCommitDevice(handle);
-        }
-        Some(Self(handle))
+        Self::try_new(DeviceType::Hip).ok()
     }
 
+    /// Create a device to run denoising on a Metal GPU, returns [None] if the
+    /// device could not be created. Use [Device::try_new] to get the reason.
     pub fn metal() -> Option<Self> {
-        let handle = unsafe { oidnNewDevice(OIDNDeviceType_OIDN_DEVICE_TYPE_METAL) };
+        Self::try_new(DeviceType::Metal).ok()
+    }
+
+    /// Create and commit a device of the given type.
+    ///
+    /// Returns the error reported by Open Image Denoise if the device could not
+    /// be created or committed, e.g. because the device type is not supported
+    /// on this machine.
+    pub fn try_new(device_type: DeviceType) -> Result<Self, OidnError> {
+        Self::commit_new(unsafe { oidnNewDevice(device_type.as_raw_oidn_device_type()) })
+    }
+
+    /// Create a device on the given physical device
+    pub fn from_physical(physical_device: &PhysicalDevice) -> Result<Self, OidnError> {
+        Self::by_id(physical_device.id())
+    }
+
+    /// Create a device on the physical device with the given ID, see
+    /// [PhysicalDevice::id]
+    pub fn by_id(physical_device_id: i32) -> Result<Self, OidnError> {
+        Self::commit_new(unsafe { oidnNewDeviceByID(physical_device_id) })
+    }
+
+    /// Create a device on the physical device with the given UUID, see
+    /// [PhysicalDevice::uuid]
+    pub fn by_uuid(uuid: [u8; 16]) -> Result<Self, OidnError> {
+        Self::commit_new(unsafe { oidnNewDeviceByUUID(uuid.as_ptr() as *const _) })
+    }
+
+    /// Create a device on the physical device with the given LUID, see
+    /// [PhysicalDevice::luid]
+    pub fn by_luid(luid: [u8; 8]) -> Result<Self, OidnError> {
+        Self::commit_new(unsafe { oidnNewDeviceByLUID(luid.as_ptr() as *const _) })
+    }
+
+    /// Create a device on the physical device with the given PCI address, see
+    /// [PhysicalDevice::pci_address]
+    pub fn by_pci_address(address: PciAddress) -> Result<Self, OidnError> {
+        Self::commit_new(unsafe {
+            oidnNewDeviceByPCIAddress(
+                address.domain,
+                address.bus,
+                address.device,
+                address.function,
+            )
+        })
+    }
+
+    /// Create a builder to configure a device before it is committed
+    pub fn builder() -> DeviceBuilder {
+        DeviceBuilder::default()
+    }
+
+    fn from_handle(handle: OIDNDevice) -> Self {
+        Self {
+            handle,
+            error_handler: Some(Arc::new(RwLock::new(None))),
+        }
+    }
+
+    /// Commits a newly created device, reporting the error if creation or
+    /// committing failed
+    fn commit_new(handle: OIDNDevice) -> Result<Self, OidnError> {
         if handle.is_null() {
-            return None;
+            return Err(creation_error());
         }
         unsafe {
             oidnCommitDevice(handle);
         }
-        Some(Self(handle))
+        let device = Self::from_handle(handle);
+        device.get_error().map_err(creation_failed)?;
+        Ok(device)
     }
 
     /// # Safety
@@ -78,31 +149,233 @@ impl Device {
     ///
     /// Raw device must be Committed using [oidnCommitDevice]
     pub unsafe fn from_raw(device: OIDNDevice) -> Self {
-        Self(device)
+        Self::from_handle(device)
     }
 
     /// # Safety
     /// Raw device must not be made invalid (e.g. by destroying it)
     pub unsafe fn raw(&self) -> OIDNDevice {
-        self.0
+        self.handle
     }
 
-    pub fn get_error(&self) -> Result<(), (Error, String)> {
+    /// Returns the first error which occurred on the device since the last
+    /// call and clears it.
+    ///
+    /// The error is reported for [Operation::Other], as the operation which
+    /// caused it isn't known.
+    pub fn get_error(&self) -> Result<(), OidnError> {
         let mut err_msg = ptr::null();
-        let err = unsafe { oidnGetDeviceError(self.0, &mut err_msg as *mut *const c_char) };
+        let err = unsafe { oidnGetDeviceError(self.handle, &mut err_msg as *mut *const c_char) };
         if OIDNError_OIDN_ERROR_NONE == err {
             Ok(())
         } else {
-            let msg = unsafe { CStr::from_ptr(err_msg).to_string_lossy().to_string() };
-            Err(((err as u32).try_into().unwrap(), msg))
+            let msg = unsafe { error_message(err_msg) };
+            Err(OidnError::new(Error::from(err), Operation::Other, msg))
+        }
+    }
+
+    /// Returns the error reported by the device for a failed `operation`, or
+    /// an error with `code` and `message` if the device reported none
+    pub(crate) fn operation_error(
+        &self,
+        operation: Operation,
+        code: Error,
+        message: &str,
+    ) -> OidnError {
+        match self.get_error() {
+            Err(error) => OidnError { operation, ..error },
+            Ok(()) => OidnError::new(code, operation, message),
+        }
+    }
+
+    /// Waits for all asynchronous operations running on the device to
+    /// complete.
+    ///
+    /// Resources borrowed by a [Pending] operation are only released once it
+    /// is waited on or dropped.
+    pub fn sync(&self) {
+        unsafe {
+            oidnSyncDevice(self.handle);
+        }
+    }
+
+    /// Queries the properties and capabilities of the device
+    pub fn info(&self) -> DeviceInfo {
+        unsafe {
+            DeviceInfo {
+                version: self.get_int(b"version\0"),
+                version_major: self.get_int(b"versionMajor\0"),
+                version_minor: self.get_int(b"versionMinor\0"),
+                version_patch: self.get_int(b"versionPatch\0"),
+                device_type: (self.get_int(b"type\0") as u32).try_into().ok(),
+                system_memory_supported: self.get_bool(b"systemMemorySupported\0"),
+                managed_memory_supported: self.get_bool(b"managedMemorySupported\0"),
+                external_memory_types: ExternalMemoryTypeFlags::from_bits(
+                    self.get_int(b"externalMemoryTypes\0") as u32,
+                ),
+            }
+        }
+    }
+
+    /// Whether the device can access host memory, e.g. for shared buffers
+    pub(crate) fn system_memory_supported(&self) -> bool {
+        unsafe { self.get_bool(b"systemMemorySupported\0") }
+    }
+
+    /// # Safety
+    /// `name` must be nul terminated
+    unsafe fn get_int(&self, name: &[u8]) -> i32 {
+        oidnGetDeviceInt(self.handle, name.as_ptr() as *const c_char)
+    }
+
+    /// # Safety
+    /// `name` must be nul terminated
+    unsafe fn get_bool(&self, name: &[u8]) -> bool {
+        oidnGetDeviceBool(self.handle, name.as_ptr() as *const c_char)
+    }
+
+    /// Sets a handler which is called whenever an error occurs on this device,
+    /// with the error code and message.
+    ///
+    /// The handler replaces any previously set handler and may be called from
+    /// any thread using the device. Errors are still reported by
+    /// [Device::get_error] as well.
+    pub fn set_error_handler(&self, handler: impl Fn(Error, &str) + Send + Sync + 'static) {
+        let error_handler = self.error_handler.as_ref().unwrap();
+        *error_handler.write().unwrap() = Some(Box::new(handler));
+        unsafe {
+            oidnSetDeviceErrorFunction(
+                self.handle,
+                Some(error_handler_trampoline),
+                Arc::as_ptr(error_handler) as *mut c_void,
+            );
+        }
+    }
+}
+
+/// An operation running asynchronously on a [Device].
+///
+/// Keeps the resources used by the operation borrowed until it has completed,
+/// dropping it waits for the device to finish.
+///
+/// Leaking a pending operation (e.g. with [std::mem::forget]) ends the borrows
+/// without waiting, so the functions starting one are unsafe.
+#[must_use = "dropping a Pending operation waits for it to complete"]
+pub struct Pending<'a> {
+    // Kept alive by the borrowed resources
+    device: OIDNDevice,
+    _borrows: PhantomData<&'a mut ()>,
+}
+
+impl<'a> Pending<'a> {
+    pub(crate) fn new(device: OIDNDevice) -> Self {
+        Self {
+            device,
+            _borrows: PhantomData,
+        }
+    }
+
+    /// Waits for the operation to complete
+    pub fn wait(self) {}
+}
+
+impl<'a> Drop for Pending<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            oidnSyncDevice(self.device);
+        }
+    }
+}
+
+unsafe impl<'a> Send for Pending<'a> {}
+
+/// Properties and capabilities of a [Device], see [Device::info].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// The combined version number of the library, e.g. 20203 for 2.2.3
+    pub version: i32,
+    pub version_major: i32,
+    pub version_minor: i32,
+    pub version_patch: i32,
+    /// The type of the device, [None] if the type is not known to this version
+    /// of the bindings
+    pub device_type: Option<DeviceType>,
+    /// Whether the device can directly access memory allocated with the
+    /// system allocator (e.g. `malloc`)
+    pub system_memory_supported: bool,
+    /// Whether the device supports buffers created with managed storage
+    pub managed_memory_supported: bool,
+    /// The external memory types which can be imported as shared buffers
+    pub external_memory_types: ExternalMemoryTypeFlags,
+}
+
+/// Returns the error that occurred while creating a device, which is reported
+/// without a device
+fn creation_error() -> OidnError {
+    let mut err_msg: *const c_char = ptr::null();
+    let err = unsafe { oidnGetDeviceError(ptr::null_mut(), &mut err_msg) };
+    let msg = unsafe { error_message(err_msg) };
+    OidnError::new(Error::from(err), Operation::DeviceCreation, msg)
+}
+
+/// Attributes an error reported by a newly created device to its creation
+fn creation_failed(error: OidnError) -> OidnError {
+    OidnError {
+        operation: Operation::DeviceCreation,
+        ..error
+    }
+}
+
+/// # Safety
+/// `message` must be null or a nul terminated string
+unsafe fn error_message(message: *const c_char) -> String {
+    if message.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(message).to_string_lossy().to_string()
+}
+
+unsafe extern "C" fn error_handler_trampoline(
+    user_ptr: *mut c_void,
+    code: OIDNError,
+    message: *const c_char,
+) {
+    let handler = &*(user_ptr as *const RwLock<Option<ErrorHandler>>);
+    let message = if message.is_null() {
+        "".into()
+    } else {
+        CStr::from_ptr(message).to_string_lossy()
+    };
+    if let Some(handler) = handler.read().unwrap().as_ref() {
+        handler(Error::from(code), &message);
+    }
+}
+
+impl Clone for Device {
+    fn clone(&self) -> Self {
+        unsafe {
+            oidnRetainDevice(self.handle);
+        }
+        Self {
+            handle: self.handle,
+            error_handler: self.error_handler.clone(),
         }
     }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
+        let error_handler = self.error_handler.take().and_then(Arc::into_inner);
         unsafe {
-            oidnReleaseDevice(self.0);
+            // References retained through the raw handle may keep the device
+            // alive after the last clone is dropped, so the handler must not
+            // be called after it is freed.
+            if let Some(error_handler) = error_handler {
+                if error_handler.into_inner().map_or(true, |h| h.is_some()) {
+                    oidnSetDeviceErrorFunction(self.handle, None, ptr::null_mut());
+                }
+            }
+            oidnReleaseDevice(self.handle);
         }
     }
 }
@@ -114,3 +387,97 @@ impl Default for Device {
 }
 
 unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
+
+/// Configures a [Device] before it is committed.
+///
+/// Some device parameters can only be set before the device is committed, e.g.
+/// to limit the number of threads used by the CPU device.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceBuilder {
+    device_type: Option<DeviceType>,
+    physical_device_id: Option<i32>,
+    num_threads: Option<i32>,
+    set_affinity: Option<bool>,
+    verbose: Option<i32>,
+}
+
+impl DeviceBuilder {
+    /// Sets the type of device to create, the default is
+    /// [DeviceType::Default].
+    ///
+    /// Overrides any previously set physical device.
+    pub fn device_type(&mut self, device_type: DeviceType) -> &mut DeviceBuilder {
+        self.device_type = Some(device_type);
+        self.physical_device_id = None;
+        self
+    }
+
+    /// Sets the physical device to create the device on.
+    ///
+    /// Overrides any previously set device type.
+    pub fn physical_device(&mut self, physical_device: &PhysicalDevice) -> &mut DeviceBuilder {
+        self.physical_device_id = Some(physical_device.id());
+        self.device_type = None;
+        self
+    }
+
+    /// Sets the maximum number of threads which the library should use (CPU
+    /// device only), 0 will set it automatically to get the best performance.
+    pub fn num_threads(&mut self, num_threads: i32) -> &mut DeviceBuilder {
+        self.num_threads = Some(num_threads);
+        self
+    }
+
+    /// Sets whether to bind software threads to hardware threads (CPU device
+    /// only).
+    ///
+    /// Enabling this can improve performance but may also interfere with other
+    /// threads pinned by the application.
+    pub fn set_affinity(&mut self, set_affinity: bool) -> &mut DeviceBuilder {
+        self.set_affinity = Some(set_affinity);
+        self
+    }
+
+    /// Sets the verbosity level of the console output between 0 and 4, when
+    /// set to 0 no output is printed.
+    pub fn verbose(&mut self, verbose: i32) -> &mut DeviceBuilder {
+        self.verbose = Some(verbose);
+        self
+    }
+
+    /// Creates the device, applies the parameters and commits it.
+    ///
+    /// Returns the error reported by Open Image Denoise if the device could not
+    /// be created or committed.
+    pub fn build(&self) -> Result<Device, OidnError> {
+        let handle = unsafe {
+            match self.physical_device_id {
+                Some(id) => oidnNewDeviceByID(id),
+                None => oidnNewDevice(
+                    self.device_type
+                        .unwrap_or(DeviceType::Default)
+                        .as_raw_oidn_device_type(),
+                ),
+            }
+        };
+        if handle.is_null() {
+            return Err(creation_error());
+        }
+        let device = Device::from_handle(handle);
+        unsafe {
+            if let Some(num_threads) = self.num_threads {
+                oidnSetDeviceInt(handle, b"numThreads\0" as *const _ as _, num_threads);
+            }
+            if let Some(set_affinity) = self.set_affinity {
+                oidnSetDeviceBool(handle, b"setAffinity\0" as *const _ as _, set_affinity);
+            }
+            if let Some(verbose) = self.verbose {
+                oidnSetDeviceInt(handle, b"verbose\0" as *const _ as _, verbose);
+            }
+            oidnCommitDevice(handle);
+        }
+        device.get_error().map_err(creation_failed)?;
+        Ok(device)
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+
+use crate::Error;
+
+/// The operation which failed with an [OidnError].
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Operation {
+    DeviceCreation,
+    BufferCreation,
+    /// Reading from or writing to a buffer
+    BufferAccess,
+    FilterCreation,
+    /// Setting an image of a filter
+    ImageSetup,
+    /// Committing the parameters of a filter
+    Commit,
+    /// Executing a filter
+    Execute,
+    /// An operation the error is not attributed to, e.g. for errors queried
+    /// with [crate::Device::get_error]
+    Other,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operation::DeviceCreation => "device creation",
+            Operation::BufferCreation => "buffer creation",
+            Operation::BufferAccess => "buffer access",
+            Operation::FilterCreation => "filter creation",
+            Operation::ImageSetup => "filter image setup",
+            Operation::Commit => "filter commit",
+            Operation::Execute => "filter execution",
+            Operation::Other => "device operation",
+        })
+    }
+}
+
+/// An error from any fallible operation of the crate, with the error code, the
+/// message reported by Open Image Denoise (or by the bindings) and the
+/// operation which failed.
+///
+/// The error code is the [std::error::Error::source] of the error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidnError {
+    pub code: Error,
+    pub message: String,
+    pub operation: Operation,
+}
+
+impl OidnError {
+    pub(crate) fn new(code: Error, operation: Operation, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            operation,
+        }
+    }
+}
+
+impl fmt::Display for OidnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{} failed: {}", self.operation, self.code)
+        } else {
+            write!(f, "{} failed: {}", self.operation, self.message)
+        }
+    }
+}
+
+impl std::error::Error for OidnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn display_falls_back_to_the_code() {
+        let error = OidnError::new(Error::OutOfMemory, Operation::BufferCreation, "");
+        assert_eq!(error.to_string(), "buffer creation failed: out of memory");
+        let error = OidnError::new(Error::InvalidArgument, Operation::Commit, "bad image");
+        assert_eq!(error.to_string(), "filter commit failed: bad image");
+    }
+
+    #[test]
+    fn source_is_the_code() {
+        let error = OidnError::new(Error::Canceled, Operation::Execute, "canceled");
+        let source = error.source().and_then(|source| source.downcast_ref());
+        assert_eq!(source, Some(&Error::Canceled));
+    }
+}
@@ -1,39 +1,395 @@
-use crate::{buffer::Buffer, device::Device, sys::*, Error, Quality};
-use std::mem;
+use crate::{
+    buffer::{as_bytes, output_buffer, Buffer, BufferElement, OutputBuffer},
+    device::{Device, Pending},
+    error::{OidnError, Operation},
+    image::{ImageDesc, ImageView, ImageViewMut},
+    progress::{progress_monitor_trampoline, ProgressMonitor},
+    sys::*,
+    Error, Format, Quality,
+};
+use std::{
+    ffi::{c_char, CString},
+    marker::PhantomData,
+    mem, ptr,
+};
+
+/// A filter of any type supported by Open Image Denoise, with its images and
+/// parameters set by name.
+///
+/// This allows using filters and parameters which are not wrapped by the
+/// crate, see the Open Image Denoise documentation for the available ones.
+/// Images and data set on the filter stay borrowed for the lifetime `'a`.
+///
+/// Names must not contain nul bytes, the methods panic otherwise.
+pub struct Filter<'a> {
+    handle: OIDNFilter,
+    pub(crate) device: Device,
+    /// The monitor passed to OIDN, owned by the filter and null if unset
+    progress_monitor: *mut ProgressMonitor,
+    _borrows: PhantomData<&'a ()>,
+}
+
+impl<'a> Filter<'a> {
+    /// Creates a new filter of the given type (e.g. `"RT"`) on the device, the
+    /// filter keeps its own reference to the device.
+    pub fn new(device: &Device, filter_type: &str) -> Result<Self, OidnError> {
+        let filter_type = c_name(filter_type);
+        let handle = unsafe { oidnNewFilter(device.handle, filter_type.as_ptr()) };
+        if handle.is_null() {
+            return Err(device.operation_error(
+                Operation::FilterCreation,
+                Error::InvalidArgument,
+                &format!("unknown filter type {filter_type:?}"),
+            ));
+        }
+        Ok(Self::from_handle(device, handle))
+    }
+
+    /// Creates a filter of a type every device supports, reporting errors
+    /// through the device
+    ///
+    /// # Safety
+    /// `filter_type` must be nul terminated
+    pub(crate) unsafe fn with_type(device: &Device, filter_type: &[u8]) -> Self {
+        let handle = oidnNewFilter(device.handle, filter_type.as_ptr() as *const c_char);
+        Self::from_handle(device, handle)
+    }
+
+    fn from_handle(device: &Device, handle: OIDNFilter) -> Self {
+        Self {
+            handle,
+            device: device.clone(),
+            progress_monitor: ptr::null_mut(),
+            _borrows: PhantomData,
+        }
+    }
+
+    /// The device the filter was created on
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// # Safety
+    /// Raw filter must not be made invalid (e.g. by destroying it)
+    pub unsafe fn raw(&self) -> OIDNFilter {
+        self.handle
+    }
+
+    /// Sets an image of the filter, e.g. `"output"` or `"color"`.
+    ///
+    /// The buffer stays mutably borrowed while the filter is used, so the
+    /// filter can write to the image. Images which are only read can be set
+    /// without borrowing the buffer mutably with [Filter::set_input_image].
+    pub fn set_image<T: BufferElement>(
+        &mut self,
+        name: &str,
+        image: ImageViewMut<'a, T>,
+    ) -> &mut Self {
+        let name = c_name(name);
+        unsafe {
+            set_raw_image(self.handle, name.as_ptr(), image.buffer(), image.desc());
+        }
+        self
+    }
+
+    /// Sets an image which the filter only reads, e.g. `"color"` or
+    /// `"albedo"`, keeping the buffer borrowed immutably.
+    ///
+    /// # Safety
+    /// The filter must not write to the image, i.e. it must not be an output
+    /// image such as `"output"`
+    pub unsafe fn set_input_image<T: BufferElement>(
+        &mut self,
+        name: &str,
+        image: ImageView<'a, T>,
+    ) -> &mut Self {
+        let name = c_name(name);
+        set_raw_image(self.handle, name.as_ptr(), image.buffer(), image.desc());
+        self
+    }
+
+    /// Removes a previously set image of the filter.
+    pub fn unset_image(&mut self, name: &str) -> &mut Self {
+        let name = c_name(name);
+        unsafe {
+            oidnUnsetFilterImage(self.handle, name.as_ptr());
+        }
+        self
+    }
+
+    /// Sets an image with the given layout without keeping its buffer
+    /// borrowed, checking the buffer is large enough to hold it and its
+    /// element type matches the format.
+    ///
+    /// # Safety
+    /// `name` must be nul terminated, and the buffer must stay valid and not
+    /// be accessed elsewhere whenever the filter is executed with the image
+    pub(crate) unsafe fn bind_image<T: BufferElement>(
+        &mut self,
+        name: &[u8],
+        buffer: &Buffer<T>,
+        desc: &ImageDesc,
+    ) -> Result<(), OidnError> {
+        ImageView::new(buffer, *desc).map_err(|error| {
+            OidnError::new(
+                error.code,
+                error.operation,
+                format!("the {} {}", image_name(name), error.message),
+            )
+        })?;
+        set_raw_image(self.handle, name.as_ptr() as *const c_char, buffer, desc);
+        Ok(())
+    }
+
+    /// Removes an image set with [Filter::bind_image]
+    ///
+    /// # Safety
+    /// `name` must be nul terminated
+    pub(crate) unsafe fn unbind_image(&mut self, name: &[u8]) {
+        oidnUnsetFilterImage(self.handle, name.as_ptr() as *const c_char);
+    }
+
+    /// Sets opaque data of the filter, e.g. custom trained weights as
+    /// `"weights"`, which is read directly from the slice.
+    pub fn set_data(&mut self, name: &str, data: &'a [u8]) -> &mut Self {
+        let name = c_name(name);
+        unsafe {
+            oidnSetSharedFilterData(
+                self.handle,
+                name.as_ptr(),
+                data.as_ptr() as *mut _,
+                data.len(),
+            );
+        }
+        self
+    }
+
+    /// Removes previously set opaque data of the filter.
+    pub fn unset_data(&mut self, name: &str) -> &mut Self {
+        let name = c_name(name);
+        unsafe {
+            oidnUnsetFilterData(self.handle, name.as_ptr());
+        }
+        self
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) -> &mut Self {
+        let name = c_name(name);
+        unsafe {
+            oidnSetFilterBool(self.handle, name.as_ptr(), value);
+        }
+        self
+    }
+
+    pub fn set_int(&mut self, name: &str, value: i32) -> &mut Self {
+        let name = c_name(name);
+        unsafe {
+            oidnSetFilterInt(self.handle, name.as_ptr(), value);
+        }
+        self
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) -> &mut Self {
+        let name = c_name(name);
+        unsafe {
+            oidnSetFilterFloat(self.handle, name.as_ptr(), value);
+        }
+        self
+    }
+
+    pub fn get_bool(&self, name: &str) -> bool {
+        let name = c_name(name);
+        unsafe { oidnGetFilterBool(self.handle, name.as_ptr()) }
+    }
+
+    pub fn get_int(&self, name: &str) -> i32 {
+        let name = c_name(name);
+        unsafe { oidnGetFilterInt(self.handle, name.as_ptr()) }
+    }
+
+    pub fn get_float(&self, name: &str) -> f32 {
+        let name = c_name(name);
+        unsafe { oidnGetFilterFloat(self.handle, name.as_ptr()) }
+    }
+
+    /// Sets a function called with the progress of filtering (from 0 to 1),
+    /// replacing any previously set function.
+    ///
+    /// Returning false cancels filtering, which then returns
+    /// [Error::Canceled]. Canceling asynchronous filtering is only reported by
+    /// the device error. See [crate::CancellationToken] for cancelling from
+    /// another thread.
+    pub fn progress_monitor(
+        &mut self,
+        monitor: impl FnMut(f64) -> bool + Send + 'static,
+    ) -> &mut Self {
+        let monitor = Box::into_raw(ProgressMonitor::new(monitor));
+        unsafe {
+            oidnSetFilterProgressMonitorFunction(
+                self.handle,
+                Some(progress_monitor_trampoline),
+                monitor as *mut _,
+            );
+            self.free_progress_monitor();
+        }
+        self.progress_monitor = monitor;
+        self
+    }
+
+    /// Removes the progress monitor function.
+    pub fn unset_progress_monitor(&mut self) -> &mut Self {
+        unsafe {
+            oidnSetFilterProgressMonitorFunction(self.handle, None, ptr::null_mut());
+            self.free_progress_monitor();
+        }
+        self
+    }
+
+    /// # Safety
+    /// The monitor must no longer be set on the filter
+    unsafe fn free_progress_monitor(&mut self) {
+        if !self.progress_monitor.is_null() {
+            drop(Box::from_raw(self.progress_monitor));
+            self.progress_monitor = ptr::null_mut();
+        }
+    }
+
+    /// Commits the images and parameters set on the filter, which must be
+    /// done before executing it after any of them changed.
+    pub fn commit(&mut self) -> Result<(), OidnError> {
+        unsafe {
+            oidnCommitFilter(self.handle);
+        }
+        check_device(&self.device, Operation::Commit)
+    }
+
+    /// Executes the filter, waiting for it to finish.
+    pub fn execute(&mut self) -> Result<(), OidnError> {
+        unsafe {
+            oidnExecuteFilter(self.handle);
+        }
+        let canceled = unsafe { self.progress_monitor.as_mut() }
+            .is_some_and(|monitor| monitor.take_canceled());
+        check_device(&self.device, Operation::Execute)?;
+        if canceled {
+            return Err(OidnError::new(
+                Error::Canceled,
+                Operation::Execute,
+                "filtering was canceled by the progress monitor",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Starts executing the filter, the caller must keep the images borrowed
+    /// until the device is synced
+    pub(crate) fn execute_async(&mut self) {
+        if let Some(monitor) = unsafe { self.progress_monitor.as_mut() } {
+            monitor.take_canceled();
+        }
+        unsafe {
+            oidnExecuteFilterAsync(self.handle);
+        }
+    }
+}
+
+impl<'a> Drop for Filter<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            oidnReleaseFilter(self.handle);
+            self.free_progress_monitor();
+        }
+    }
+}
+
+unsafe impl<'a> Send for Filter<'a> {}
+
+/// Converts the name of an image or parameter to a C string
+///
+/// # Panics
+/// - if the name contains a nul byte
+fn c_name(name: &str) -> CString {
+    CString::new(name).expect("filter names must not contain nul bytes")
+}
 
 /// A generic ray tracing denoising filter for denoising
 /// images produces with Monte Carlo ray tracing methods
 /// such as path tracing.
-pub struct RayTracing<'a> {
-    handle: OIDNFilter,
-    device: &'a Device,
-    albedo: Option<Buffer>,
-    normal: Option<Buffer>,
+pub struct RayTracing {
+    inner: ImageFilter<RayTracingSetup>,
+}
+
+/// The parameters and auxiliary images of a [RayTracing] filter
+struct RayTracingSetup {
+    albedo: Option<AuxBuffer>,
+    normal: Option<AuxBuffer>,
     hdr: bool,
     input_scale: f32,
     srgb: bool,
     clean_aux: bool,
-    img_dims: (usize, usize, usize),
+    albedo_desc: ImageDesc,
+    normal_desc: ImageDesc,
     filter_quality: OIDNQuality,
+    bound_albedo: BoundImage,
+    bound_normal: BoundImage,
 }
 
-impl<'a> RayTracing<'a> {
-    pub fn new(device: &'a Device) -> RayTracing<'a> {
+impl FilterSetup for RayTracingSetup {
+    fn set_params(&self, filter: &mut Filter) {
+        filter
+            .set_bool("hdr", self.hdr)
+            .set_float("inputScale", self.input_scale)
+            .set_bool("srgb", self.srgb)
+            .set_bool("cleanAux", self.clean_aux)
+            .set_int("quality", self.filter_quality as i32);
+    }
+
+    fn set_images(&mut self, filter: &mut Filter) -> Result<bool, OidnError> {
+        // No use supplying normal if albedo was not also given.
+        let normal = self.albedo.as_ref().and(self.normal.as_ref());
         unsafe {
-            oidnRetainDevice(device.0);
+            let albedo_changed = match &self.albedo {
+                Some(albedo) => albedo.set(
+                    filter,
+                    &mut self.bound_albedo,
+                    b"albedo\0",
+                    &self.albedo_desc,
+                )?,
+                None => self.bound_albedo.unset(filter, b"albedo\0"),
+            };
+            let normal_changed = match normal {
+                Some(normal) => normal.set(
+                    filter,
+                    &mut self.bound_normal,
+                    b"normal\0",
+                    &self.normal_desc,
+                )?,
+                None => self.bound_normal.unset(filter, b"normal\0"),
+            };
+            Ok(albedo_changed || normal_changed)
         }
-        let filter = unsafe { oidnNewFilter(device.0, b"RT\0" as *const _ as _) };
-        RayTracing {
-            handle: filter,
-            device,
+    }
+}
+
+impl RayTracing {
+    /// Creates a new filter on the device, the filter keeps its own reference
+    /// to the device.
+    pub fn new(device: &Device) -> RayTracing {
+        let setup = RayTracingSetup {
             albedo: None,
             normal: None,
             hdr: false,
             input_scale: f32::NAN,
             srgb: false,
             clean_aux: false,
-            img_dims: (0, 0, 0),
+            albedo_desc: ImageDesc::new(0, 0, Format::Float3),
+            normal_desc: ImageDesc::new(0, 0, Format::Float3),
             filter_quality: 0,
+            bound_albedo: BoundImage::default(),
+            bound_normal: BoundImage::default(),
+        };
+        RayTracing {
+            inner: unsafe { ImageFilter::new(device, b"RT\0", setup) },
         }
     }
 
@@ -43,8 +399,9 @@ impl<'a> RayTracing<'a> {
     /// some devices will not support this and so
     /// the result (and performance) will stay the same as high.
     /// Balanced is recommended for realtime usages.
-    pub fn filter_quality(&mut self, quality: Quality) -> &mut RayTracing<'a> {
-        self.filter_quality = quality.as_raw_oidn_quality();
+    pub fn filter_quality(&mut self, quality: Quality) -> &mut RayTracing {
+        self.inner.setup.filter_quality = quality.as_raw_oidn_quality();
+        self.inner.params_dirty = true;
         self
     }
 
@@ -55,64 +412,38 @@ impl<'a> RayTracing<'a> {
     /// *world-space* or *view-space* vectors with arbitrary length, values
     /// in `[-1, 1]`.
     ///
-    /// # Panics
-    /// - if resource creation fails
-    pub fn albedo_normal(&mut self, albedo: &[f32], normal: &[f32]) -> &mut RayTracing<'a> {
-        match self.albedo.as_mut().and_then(|buf| {
-            if buf.size == albedo.len() {
-                Some(buf)
-            } else {
-                None
-            }
-        }) {
-            None => {
-                self.albedo = Some(self.device.create_buffer(albedo).unwrap());
-            }
-            Some(buf) => {
-                buf.write(albedo)
-                    .expect("we check if the size is the same already");
-            }
-        }
-        match self.normal.as_mut().and_then(|buf| {
-            if buf.size == normal.len() {
-                Some(buf)
-            } else {
-                None
-            }
-        }) {
-            None => {
-                self.albedo = Some(self.device.create_buffer(normal).unwrap());
-            }
-            Some(buf) => {
-                buf.write(normal)
-                    .expect("we check if the size is the same already");
-            }
-        }
-        self
+    /// Fails if the images can't be copied to device buffers.
+    pub fn albedo_normal<T: BufferElement>(
+        &mut self,
+        albedo: &[T],
+        normal: &[T],
+    ) -> Result<&mut RayTracing, OidnError> {
+        AuxBuffer::update(
+            &mut self.inner.setup.albedo,
+            &self.inner.filter.device,
+            albedo,
+        )?;
+        AuxBuffer::update(
+            &mut self.inner.setup.normal,
+            &self.inner.filter.device,
+            normal,
+        )?;
+        self.inner.uncommitted = true;
+        Ok(self)
     }
 
     /// Set an input auxiliary image containing the albedo per pixel (three
     /// channels, values in `[0, 1]`).
     ///
-    /// # Panics
-    /// - if resource creation fails
-    pub fn albedo(&mut self, albedo: &[f32]) -> &mut RayTracing<'a> {
-        match self.albedo.as_mut().and_then(|buf| {
-            if buf.size == albedo.len() {
-                Some(buf)
-            } else {
-                None
-            }
-        }) {
-            None => {
-                self.albedo = Some(self.device.create_buffer(albedo).unwrap());
-            }
-            Some(buf) => {
-                buf.write(albedo)
-                    .expect("we check if the size is the same already");
-            }
-        }
-        self
+    /// Fails if the image can't be copied to a device buffer.
+    pub fn albedo<T: BufferElement>(&mut self, albedo: &[T]) -> Result<&mut RayTracing, OidnError> {
+        AuxBuffer::update(
+            &mut self.inner.setup.albedo,
+            &self.inner.filter.device,
+            albedo,
+        )?;
+        self.inner.uncommitted = true;
+        Ok(self)
     }
     /// Set input auxiliary buffer containing the albedo and normals.
     ///
@@ -123,18 +454,19 @@ impl<'a> RayTracing<'a> {
     ///
     /// This function is the same as [RayTracing::albedo_normal] but takes buffers instead
     ///
-    /// Returns [None] if either buffer was not created by this device
-    pub fn albedo_normal_buffer(
+    /// Fails with [Error::InvalidArgument] if either buffer was not created by
+    /// the device of the filter.
+    pub fn albedo_normal_buffer<T: BufferElement>(
         &mut self,
-        albedo: Buffer,
-        normal: Buffer,
-    ) -> Option<&mut RayTracing<'a>> {
-        if albedo.id != self.device.0 as isize || normal.id != self.device.0 as isize {
-            return None;
-        }
-        self.albedo = Some(albedo);
-        self.normal = Some(normal);
-        Some(self)
+        albedo: Buffer<T>,
+        normal: Buffer<T>,
+    ) -> Result<&mut RayTracing, OidnError> {
+        check_buffer_device(&self.inner.filter.device, &albedo, b"albedo\0")?;
+        check_buffer_device(&self.inner.filter.device, &normal, b"normal\0")?;
+        self.inner.setup.albedo = Some(AuxBuffer::new(albedo));
+        self.inner.setup.normal = Some(AuxBuffer::new(normal));
+        self.inner.uncommitted = true;
+        Ok(self)
     }
 
     /// Set an input auxiliary buffer containing the albedo per pixel (three
@@ -142,24 +474,29 @@ impl<'a> RayTracing<'a> {
     ///
     /// This function is the same as [RayTracing::albedo] but takes buffers instead
     ///
-    /// Returns [None] if albedo buffer was not created by this device
-    pub fn albedo_buffer(&mut self, albedo: Buffer) -> Option<&mut RayTracing<'a>> {
-        if albedo.id != self.device.0 as isize {
-            return None;
-        }
-        self.albedo = Some(albedo);
-        Some(self)
+    /// Fails with [Error::InvalidArgument] if the buffer was not created by the
+    /// device of the filter.
+    pub fn albedo_buffer<T: BufferElement>(
+        &mut self,
+        albedo: Buffer<T>,
+    ) -> Result<&mut RayTracing, OidnError> {
+        check_buffer_device(&self.inner.filter.device, &albedo, b"albedo\0")?;
+        self.inner.setup.albedo = Some(AuxBuffer::new(albedo));
+        self.inner.uncommitted = true;
+        Ok(self)
     }
 
     /// Set whether the color is HDR.
-    pub fn hdr(&mut self, hdr: bool) -> &mut RayTracing<'a> {
-        self.hdr = hdr;
+    pub fn hdr(&mut self, hdr: bool) -> &mut RayTracing {
+        self.inner.setup.hdr = hdr;
+        self.inner.params_dirty = true;
         self
     }
 
     #[deprecated(since = "1.3.1", note = "Please use RayTracing::input_scale instead")]
-    pub fn hdr_scale(&mut self, hdr_scale: f32) -> &mut RayTracing<'a> {
-        self.input_scale = hdr_scale;
+    pub fn hdr_scale(&mut self, hdr_scale: f32) -> &mut RayTracing {
+        self.inner.setup.input_scale = hdr_scale;
+        self.inner.params_dirty = true;
         self
     }
 
@@ -170,9 +507,11 @@ impl<'a> RayTracing<'a> {
     /// expected range. E.g. for mapping HDR values to physical units (which
     /// affects the quality of the output but not the range of the output
     /// values). If not set, the scale is computed implicitly for HDR images
-    /// or set to 1 otherwise
-    pub fn input_scale(&mut self, input_scale: f32) -> &mut RayTracing<'a> {
-        self.input_scale = input_scale;
+    /// or set to 1 otherwise. Open Image Denoise does not report the scale it
+    /// computes, it is recomputed from the color image on each execution.
+    pub fn input_scale(&mut self, input_scale: f32) -> &mut RayTracing {
+        self.inner.setup.input_scale = input_scale;
+        self.inner.params_dirty = true;
         self
     }
 
@@ -180,8 +519,9 @@ impl<'a> RayTracing<'a> {
     /// only) or is linear.
     ///
     /// The output will be encoded with the same curve.
-    pub fn srgb(&mut self, srgb: bool) -> &mut RayTracing<'a> {
-        self.srgb = srgb;
+    pub fn srgb(&mut self, srgb: bool) -> &mut RayTracing {
+        self.inner.setup.srgb = srgb;
+        self.inner.params_dirty = true;
         self
     }
 
@@ -190,190 +530,669 @@ impl<'a> RayTracing<'a> {
     ///
     /// Recommended for highest quality but should not be enabled for noisy
     /// auxiliary images to avoid residual noise.
-    pub fn clean_aux(&mut self, clean_aux: bool) -> &mut RayTracing<'a> {
-        self.clean_aux = clean_aux;
+    pub fn clean_aux(&mut self, clean_aux: bool) -> &mut RayTracing {
+        self.inner.setup.clean_aux = clean_aux;
+        self.inner.params_dirty = true;
         self
     }
 
-    /// sets the dimensions of the denoising image, if new width * new height
-    /// does not equal old width * old height
-    pub fn image_dimensions(&mut self, width: usize, height: usize) -> &mut RayTracing<'a> {
-        let buffer_dims = 3 * width * height;
-        match &self.albedo {
-            None => {}
-            Some(buffer) => {
-                if buffer.size != buffer_dims {
-                    self.albedo = None;
-                }
-            }
-        }
-        match &self.normal {
-            None => {}
-            Some(buffer) => {
-                if buffer.size != buffer_dims {
-                    self.normal = None;
-                }
-            }
+    /// Sets a function called with the progress of filtering (from 0 to 1),
+    /// replacing any previously set function.
+    ///
+    /// Returning false cancels filtering, which then returns
+    /// [Error::Canceled]. Canceling asynchronous filtering is only reported by
+    /// the device error. See [crate::CancellationToken] for cancelling from
+    /// another thread.
+    pub fn progress_monitor(
+        &mut self,
+        monitor: impl FnMut(f64) -> bool + Send + 'static,
+    ) -> &mut RayTracing {
+        self.inner.filter.progress_monitor(monitor);
+        self
+    }
+
+    /// Removes the progress monitor function.
+    pub fn unset_progress_monitor(&mut self) -> &mut RayTracing {
+        self.inner.filter.unset_progress_monitor();
+        self
+    }
+
+    /// Sets the pixel format of the color image, the default is
+    /// [Format::Float3].
+    ///
+    /// The color image must have three channels, an alpha channel (e.g. with
+    /// [Format::Float4]) is ignored.
+    pub fn color_format(&mut self, format: Format) -> &mut RayTracing {
+        self.inner.color_desc.format = format;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the pixel format of the albedo image, the default is
+    /// [Format::Float3].
+    pub fn albedo_format(&mut self, format: Format) -> &mut RayTracing {
+        self.inner.setup.albedo_desc.format = format;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the pixel format of the normal image, the default is
+    /// [Format::Float3].
+    pub fn normal_format(&mut self, format: Format) -> &mut RayTracing {
+        self.inner.setup.normal_desc.format = format;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the pixel format of the output image, the default is
+    /// [Format::Float3].
+    ///
+    /// When filtering in place the output format must be the same as the color
+    /// format.
+    pub fn output_format(&mut self, format: Format) -> &mut RayTracing {
+        self.inner.output_desc.format = format;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the layout of the color image, including its dimensions.
+    ///
+    /// This allows filtering images with padding or interleaved with other
+    /// data, e.g. the RGB channels of an RGBA framebuffer.
+    pub fn color_desc(&mut self, desc: ImageDesc) -> &mut RayTracing {
+        self.inner.color_desc = desc;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the layout of the albedo image, including its dimensions.
+    pub fn albedo_desc(&mut self, desc: ImageDesc) -> &mut RayTracing {
+        self.inner.setup.albedo_desc = desc;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the layout of the normal image, including its dimensions.
+    pub fn normal_desc(&mut self, desc: ImageDesc) -> &mut RayTracing {
+        self.inner.setup.normal_desc = desc;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the layout of the output image, including its dimensions.
+    ///
+    /// When filtering in place the output layout must be the same as the color
+    /// layout.
+    pub fn output_desc(&mut self, desc: ImageDesc) -> &mut RayTracing {
+        self.inner.output_desc = desc;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the dimensions of all images, dropping the auxiliary and cached
+    /// buffers the new images no longer fit in.
+    pub fn image_dimensions(&mut self, width: usize, height: usize) -> &mut RayTracing {
+        self.inner.image_dimensions(width, height);
+        let setup = &mut self.inner.setup;
+        for desc in [&mut setup.albedo_desc, &mut setup.normal_desc] {
+            desc.width = width;
+            desc.height = height;
         }
-        self.img_dims = (width, height, buffer_dims);
+        // Only drop buffers the new images no longer fit in, a buffer larger
+        // than the image may be padded or hold a strided image.
+        setup.albedo = setup
+            .albedo
+            .take()
+            .filter(|aux| setup.albedo_desc.validate(aux.buffer.byte_size()).is_ok());
+        setup.normal = setup
+            .normal
+            .take()
+            .filter(|aux| setup.normal_desc.validate(aux.buffer.byte_size()).is_ok());
         self
     }
 
-    pub fn filter(&self, color: &[f32], output: &mut [f32]) -> Result<(), Error> {
-        self.execute_filter(Some(color), output)
+    /// Filters the color image into the output image.
+    ///
+    /// If the device can't access host memory the images are copied through
+    /// device buffers, which are kept and reused while the image size stays
+    /// the same.
+    pub fn filter<T: BufferElement>(
+        &mut self,
+        color: &[T],
+        output: &mut [T],
+    ) -> Result<(), OidnError> {
+        self.inner.filter_slices(Some(color), output)
+    }
+
+    pub fn filter_buffer<T: BufferElement>(
+        &mut self,
+        color: &Buffer<T>,
+        output: &mut impl OutputBuffer<T>,
+    ) -> Result<(), OidnError> {
+        self.inner.filter_buffer(Some(color), output_buffer(output))
+    }
+
+    /// Filters the color image in place, reusing device buffers the same as
+    /// [RayTracing::filter].
+    pub fn filter_in_place<T: BufferElement>(&mut self, color: &mut [T]) -> Result<(), OidnError> {
+        self.inner.filter_slices(None, color)
+    }
+
+    pub fn filter_in_place_buffer<T: BufferElement>(
+        &mut self,
+        color: &mut impl OutputBuffer<T>,
+    ) -> Result<(), OidnError> {
+        self.inner.filter_buffer(None, output_buffer(color))
+    }
+
+    /// Starts filtering asynchronously, the same as [RayTracing::filter_buffer]
+    /// otherwise.
+    ///
+    /// The buffers and the filter stay borrowed until the returned [Pending]
+    /// is waited on or dropped, which waits for the filter to finish.
+    ///
+    /// # Safety
+    /// The returned [Pending] must not be leaked (e.g. with [std::mem::forget]),
+    /// otherwise the buffers, including shared buffers and their memory, could
+    /// be freed or accessed while the filter still uses them.
+    pub unsafe fn filter_buffer_async<'b, T: BufferElement>(
+        &'b mut self,
+        color: &'b Buffer<T>,
+        output: &'b mut impl OutputBuffer<T>,
+    ) -> Result<Pending<'b>, OidnError> {
+        self.inner
+            .filter_buffer_async(Some(color), output_buffer(output))
+    }
+
+    /// Starts filtering asynchronously, the same as
+    /// [RayTracing::filter_in_place_buffer] otherwise.
+    ///
+    /// The buffer and the filter stay borrowed until the returned [Pending] is
+    /// waited on or dropped, which waits for the filter to finish.
+    ///
+    /// # Safety
+    /// See [RayTracing::filter_buffer_async].
+    pub unsafe fn filter_in_place_buffer_async<'b, T: BufferElement>(
+        &'b mut self,
+        color: &'b mut impl OutputBuffer<T>,
+    ) -> Result<Pending<'b>, OidnError> {
+        self.inner.filter_buffer_async(None, output_buffer(color))
+    }
+
+    /// Sets the auxiliary images and parameters on the filter and commits it.
+    ///
+    /// Filtering commits the filter automatically if any parameter or image
+    /// changed since the last commit, committing explicitly allows doing the
+    /// possibly expensive initialization ahead of time. The color and output
+    /// images are only set when filtering, the images of the last filtering
+    /// stay set on the filter until then with the layouts they were set with.
+    pub fn commit(&mut self) -> Result<(), OidnError> {
+        self.inner.commit()
+    }
+
+    /// The alignment in pixels of the tiles the committed filter splits images
+    /// into, or `None` if it was not committed since parameters or images
+    /// changed.
+    pub fn tile_alignment(&self) -> Option<usize> {
+        self.inner
+            .committed()
+            .then(|| self.inner.filter.get_int("tileAlignment") as usize)
     }
 
-    pub fn filter_buffer(&self, color: &Buffer, output: &mut Buffer) -> Result<(), Error> {
-        self.execute_filter_buffer(Some(color), output)
+    /// The overlap in pixels of the tiles the committed filter splits images
+    /// into, or `None` if it was not committed since parameters or images
+    /// changed.
+    pub fn tile_overlap(&self) -> Option<usize> {
+        self.inner
+            .committed()
+            .then(|| self.inner.filter.get_int("tileOverlap") as usize)
+    }
+}
+
+unsafe impl Send for RayTracing {}
+
+/// The parameters and images other than the color and output of a filter
+/// wrapped by [ImageFilter].
+pub(crate) trait FilterSetup {
+    /// Sets the parameters on the filter
+    fn set_params(&self, filter: &mut Filter);
+
+    /// Sets the auxiliary images on the filter, returns whether any image
+    /// changed
+    fn set_images(&mut self, _filter: &mut Filter) -> Result<bool, OidnError> {
+        Ok(false)
     }
+}
+
+/// A filter with a color and output image, shared by the filter wrappers.
+///
+/// Slices being filtered are shared with the device if possible, otherwise
+/// copied through device buffers, and the buffers are kept for filtering the
+/// same slices again. Images and parameters are only set and committed again
+/// if they changed.
+pub(crate) struct ImageFilter<S> {
+    pub(crate) filter: Filter<'static>,
+    pub(crate) setup: S,
+    pub(crate) color_desc: ImageDesc,
+    pub(crate) output_desc: ImageDesc,
+    /// Buffers reused when the slices have to be copied
+    color_buffer: Option<Buffer<u8>>,
+    output_buffer: Option<Buffer<u8>>,
+    /// Buffers reused when the slices are shared
+    shared_color: Option<SharedSlice>,
+    shared_output: Option<SharedSlice>,
+    bound_color: BoundImage,
+    bound_output: BoundImage,
+    /// Whether the parameters changed since they were last set on the filter
+    pub(crate) params_dirty: bool,
+    /// Whether the images changed since the filter was committed
+    pub(crate) uncommitted: bool,
+}
 
-    pub fn filter_in_place(&self, color: &mut [f32]) -> Result<(), Error> {
-        self.execute_filter(None, color)
+impl<S: FilterSetup> ImageFilter<S> {
+    /// # Safety
+    /// `filter_type` must be nul terminated, see [Filter::with_type]
+    pub(crate) unsafe fn new(device: &Device, filter_type: &[u8], setup: S) -> Self {
+        Self {
+            filter: Filter::with_type(device, filter_type),
+            setup,
+            color_desc: ImageDesc::new(0, 0, Format::Float3),
+            output_desc: ImageDesc::new(0, 0, Format::Float3),
+            color_buffer: None,
+            output_buffer: None,
+            shared_color: None,
+            shared_output: None,
+            bound_color: BoundImage::default(),
+            bound_output: BoundImage::default(),
+            params_dirty: true,
+            uncommitted: true,
+        }
     }
 
-    pub fn filter_in_place_buffer(&self, color: &mut Buffer) -> Result<(), Error> {
-        self.execute_filter_buffer(None, color)
+    /// Sets the dimensions of the color and output images, dropping the
+    /// cached buffers they no longer fit in
+    pub(crate) fn image_dimensions(&mut self, width: usize, height: usize) {
+        for desc in [&mut self.color_desc, &mut self.output_desc] {
+            desc.width = width;
+            desc.height = height;
+        }
+        self.uncommitted = true;
+        self.color_buffer = self
+            .color_buffer
+            .take()
+            .filter(|buffer| self.color_desc.validate(buffer.byte_size()).is_ok());
+        self.output_buffer = self
+            .output_buffer
+            .take()
+            .filter(|buffer| self.output_desc.validate(buffer.byte_size()).is_ok());
     }
 
-    fn execute_filter(&self, color: Option<&[f32]>, output: &mut [f32]) -> Result<(), Error> {
+    /// Filters the color slice into the output, or the output in place if
+    /// there is no color
+    pub(crate) fn filter_slices<T: BufferElement>(
+        &mut self,
+        color: Option<&[T]>,
+        output: &mut [T],
+    ) -> Result<(), OidnError> {
+        if self.filter.device.system_memory_supported() {
+            // The filter only reads from the color image, so it can share the
+            // memory of the slices without copying. The shared buffers are
+            // kept, so the images are not set again for the same slices.
+            let device = &self.filter.device;
+            let color = match color {
+                None => None,
+                Some(color) => Some(unsafe {
+                    SharedSlice::take(
+                        &mut self.shared_color,
+                        device,
+                        color.as_ptr() as *mut T,
+                        color.len(),
+                    )
+                }?),
+            };
+            let mut out = unsafe {
+                SharedSlice::take(
+                    &mut self.shared_output,
+                    device,
+                    output.as_mut_ptr(),
+                    output.len(),
+                )
+            }?;
+            let result = self.filter_buffer(color.as_ref(), &mut out);
+            if let Some(color) = color {
+                self.shared_color = Some(SharedSlice::new(color));
+            }
+            self.shared_output = Some(SharedSlice::new(out));
+            return result;
+        }
         let color = match color {
             None => None,
-            Some(color) => Some(self.device.create_buffer(color).ok_or(Error::OutOfMemory)?),
-        };
-        let mut out = self
-            .device
-            .create_buffer(output)
-            .ok_or(Error::OutOfMemory)?;
-        self.execute_filter_buffer(color.as_ref(), &mut out)?;
-        unsafe {
-            oidnReadBuffer(
-                out.buf,
-                0,
-                out.size * mem::size_of::<f32>(),
-                output.as_mut_ptr() as *mut _,
-            )
+            Some(color) => {
+                let mut buffer =
+                    cached_buffer(&mut self.color_buffer, &self.filter.device, color.len())?;
+                buffer
+                    .write(color)
+                    .expect("the buffer was created with the color size");
+                Some(buffer)
+            }
         };
-        Ok(())
+        let mut out = cached_buffer(&mut self.output_buffer, &self.filter.device, output.len())?;
+        // Filtering in place reads the color image from the output, and bytes
+        // between the output pixels must be kept.
+        if color.is_none() || !self.output_desc.covers(mem::size_of_val(output)) {
+            out.write(output)
+                .expect("the buffer was created with the output size");
+        }
+        let result = self.filter_buffer(color.as_ref(), &mut out);
+        if result.is_ok() {
+            out.read(output)
+                .expect("the buffer was created with the output size");
+        }
+        if let Some(color) = color {
+            self.color_buffer = Some(color.into_bytes());
+        }
+        self.output_buffer = Some(out.into_bytes());
+        result
     }
 
-    fn execute_filter_buffer(
-        &self,
-        color: Option<&Buffer>,
-        output: &mut Buffer,
-    ) -> Result<(), Error> {
-        if let Some(alb) = &self.albedo {
-            if alb.size != self.img_dims.2 {
-                return Err(Error::InvalidImageDimensions);
-            }
-            unsafe {
-                oidnSetFilterImage(
-                    self.handle,
-                    b"albedo\0" as *const _ as _,
-                    alb.buf,
-                    OIDNFormat_OIDN_FORMAT_FLOAT3,
-                    self.img_dims.0 as _,
-                    self.img_dims.1 as _,
-                    0,
-                    0,
-                    0,
-                );
-            }
+    /// Filters the color buffer into the output, or the output in place if
+    /// there is no color
+    pub(crate) fn filter_buffer<T: BufferElement>(
+        &mut self,
+        color: Option<&Buffer<T>>,
+        output: &mut Buffer<T>,
+    ) -> Result<(), OidnError> {
+        self.prepare(color, output)?;
+        self.filter.execute()
+    }
 
-            // No use supplying normal if albedo was
-            // not also given.
-            if let Some(norm) = &self.normal {
-                if norm.size != self.img_dims.2 {
-                    return Err(Error::InvalidImageDimensions);
-                }
-                unsafe {
-                    oidnSetFilterImage(
-                        self.handle,
-                        b"normal\0" as *const _ as _,
-                        norm.buf,
-                        OIDNFormat_OIDN_FORMAT_FLOAT3,
-                        self.img_dims.0 as _,
-                        self.img_dims.1 as _,
-                        0,
-                        0,
-                        0,
-                    );
-                }
-            }
+    /// Starts filtering the same as [ImageFilter::filter_buffer], the returned
+    /// [Pending] must not be leaked
+    pub(crate) fn filter_buffer_async<'b, T: BufferElement>(
+        &'b mut self,
+        color: Option<&'b Buffer<T>>,
+        output: &'b mut Buffer<T>,
+    ) -> Result<Pending<'b>, OidnError> {
+        self.prepare(color, output)?;
+        self.filter.execute_async();
+        Ok(Pending::new(self.filter.device.handle))
+    }
+
+    /// Sets the auxiliary images and the parameters if they changed and
+    /// commits the filter
+    pub(crate) fn commit(&mut self) -> Result<(), OidnError> {
+        self.setup.set_images(&mut self.filter)?;
+        self.commit_filter()
+    }
+
+    /// Sets the parameters if they changed and commits the filter
+    fn commit_filter(&mut self) -> Result<(), OidnError> {
+        if self.params_dirty {
+            self.setup.set_params(&mut self.filter);
         }
+        self.filter.commit()?;
+        self.params_dirty = false;
+        self.uncommitted = false;
+        Ok(())
+    }
+
+    /// Whether the filter was committed since parameters or images changed
+    pub(crate) fn committed(&self) -> bool {
+        !self.uncommitted && !self.params_dirty
+    }
+
+    /// Sets the images of the filter and commits it, skipping both if nothing
+    /// changed since the last time
+    fn prepare<T: BufferElement>(
+        &mut self,
+        color: Option<&Buffer<T>>,
+        output: &mut Buffer<T>,
+    ) -> Result<(), OidnError> {
         let color_buffer = match color {
-            Some(color) => {
-                if color.size != self.img_dims.2 {
-                    return Err(Error::InvalidImageDimensions);
-                }
-                color
-            }
+            Some(color) => color,
             None => {
-                if output.size != self.img_dims.2 {
-                    return Err(Error::InvalidImageDimensions);
+                if self.color_desc != self.output_desc {
+                    return Err(OidnError::new(
+                        Error::InvalidImageDimensions,
+                        Operation::ImageSetup,
+                        "the color and output layouts differ when filtering in place",
+                    ));
                 }
-                // actually this is a needed borrow, the compiler complains otherwise
-                #[allow(clippy::needless_borrow)]
-                &output
+                &*output
             }
         };
+        self.uncommitted |= self.setup.set_images(&mut self.filter)?;
         unsafe {
-            oidnSetFilterImage(
-                self.handle,
-                b"color\0" as *const _ as _,
-                color_buffer.buf,
-                OIDNFormat_OIDN_FORMAT_FLOAT3,
-                self.img_dims.0 as _,
-                self.img_dims.1 as _,
-                0,
-                0,
-                0,
-            );
+            self.uncommitted |= self.bound_color.set(
+                &mut self.filter,
+                b"color\0",
+                color_buffer,
+                &self.color_desc,
+            )?;
+            self.uncommitted |=
+                self.bound_output
+                    .set(&mut self.filter, b"output\0", output, &self.output_desc)?;
         }
+        if self.uncommitted || self.params_dirty {
+            self.commit_filter()?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the error reported by the device, if any, as an error of
+/// `operation`
+pub(crate) fn check_device(device: &Device, operation: Operation) -> Result<(), OidnError> {
+    device
+        .get_error()
+        .map_err(|error| OidnError { operation, ..error })
+}
+
+/// Checks the buffer of the image `name` was created by `device`
+fn check_buffer_device<T: BufferElement>(
+    device: &Device,
+    buffer: &Buffer<T>,
+    name: &[u8],
+) -> Result<(), OidnError> {
+    if buffer.device.handle != device.handle {
+        return Err(OidnError::new(
+            Error::InvalidArgument,
+            Operation::ImageSetup,
+            format!(
+                "the {} buffer was not created by the device of the filter",
+                image_name(name)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn format_error(name: &[u8], format: Format) -> OidnError {
+    OidnError::new(
+        Error::InvalidArgument,
+        Operation::ImageSetup,
+        format!(
+            "the {} image format {format:?} does not match the buffer element type",
+            image_name(name)
+        ),
+    )
+}
+
+/// The name of an image without the nul terminator
+fn image_name(name: &[u8]) -> std::borrow::Cow<'_, str> {
+    String::from_utf8_lossy(name.strip_suffix(b"\0").unwrap_or(name))
+}
 
-        if output.size != self.img_dims.2 {
-            return Err(Error::InvalidImageDimensions);
+/// Takes the buffer cached in `slot` if it holds `len` elements, otherwise
+/// allocates a new buffer, leaving its contents to be written by the caller.
+fn cached_buffer<T: BufferElement>(
+    slot: &mut Option<Buffer<u8>>,
+    device: &Device,
+    len: usize,
+) -> Result<Buffer<T>, OidnError> {
+    match slot.take() {
+        Some(buffer) if buffer.byte_size() == len * mem::size_of::<T>() => {
+            Ok(Buffer::from_bytes(buffer))
         }
-        unsafe {
-            oidnSetFilterImage(
-                self.handle,
-                b"output\0" as *const _ as _,
-                output.buf,
-                OIDNFormat_OIDN_FORMAT_FLOAT3,
-                self.img_dims.0 as _,
-                self.img_dims.1 as _,
-                0,
-                0,
-                0,
-            );
-            oidnSetFilterBool(self.handle, b"hdr\0" as *const _ as _, self.hdr);
-            oidnSetFilterFloat(
-                self.handle,
-                b"inputScale\0" as *const _ as _,
-                self.input_scale,
-            );
-            oidnSetFilterBool(self.handle, b"srgb\0" as *const _ as _, self.srgb);
-            oidnSetFilterBool(self.handle, b"clean_aux\0" as *const _ as _, self.clean_aux);
+        _ => device.allocate_buffer(len),
+    }
+}
 
-            oidnSetFilterInt(
-                self.handle,
-                b"quality\0" as *const _ as _,
-                self.filter_quality as i32,
-            );
+/// # Safety
+/// `name` must be nul terminated and the image must fit in the buffer
+unsafe fn set_raw_image<T: BufferElement>(
+    filter: OIDNFilter,
+    name: *const c_char,
+    buffer: &Buffer<T>,
+    desc: &ImageDesc,
+) {
+    oidnSetFilterImage(
+        filter,
+        name,
+        buffer.buf,
+        desc.format.as_raw_oidn_format(),
+        desc.width,
+        desc.height,
+        desc.byte_offset,
+        desc.pixel_byte_stride,
+        desc.row_byte_stride,
+    );
+}
 
-            oidnCommitFilter(self.handle);
-            oidnExecuteFilter(self.handle);
+/// An auxiliary image buffer owned by a filter, with its element type erased.
+struct AuxBuffer {
+    buffer: Buffer<u8>,
+    formats: &'static [Format],
+}
+
+impl AuxBuffer {
+    fn new<T: BufferElement>(buffer: Buffer<T>) -> Self {
+        Self {
+            buffer: buffer.into_bytes(),
+            formats: T::FORMATS,
         }
-        Ok(())
+    }
+
+    /// Writes the contents to the buffer in `slot` if it has the same size and
+    /// element type, otherwise replaces it with a new buffer.
+    ///
+    /// Fails if a new buffer can't be created.
+    fn update<T: BufferElement>(
+        slot: &mut Option<AuxBuffer>,
+        device: &Device,
+        contents: &[T],
+    ) -> Result<(), OidnError> {
+        let contents_size = mem::size_of_val(contents);
+        match slot
+            .as_mut()
+            .filter(|aux| aux.formats == T::FORMATS && aux.buffer.byte_size() == contents_size)
+        {
+            None => {
+                *slot = Some(AuxBuffer::new(device.create_buffer(contents)?));
+                Ok(())
+            }
+            Some(aux) => aux.buffer.write(as_bytes(contents)),
+        }
+    }
+
+    /// Sets the image on the filter, see [BoundImage::set].
+    ///
+    /// # Safety
+    /// `name` must be nul terminated
+    unsafe fn set(
+        &self,
+        filter: &mut Filter,
+        bound: &mut BoundImage,
+        name: &[u8],
+        desc: &ImageDesc,
+    ) -> Result<bool, OidnError> {
+        if !self.formats.contains(&desc.format) {
+            return Err(format_error(name, desc.format));
+        }
+        bound.set(filter, name, &self.buffer, desc)
     }
 }
 
-impl<'a> Drop for RayTracing<'a> {
-    fn drop(&mut self) {
-        unsafe {
-            oidnReleaseFilter(self.handle);
-            oidnReleaseDevice(self.device.0);
+/// A buffer sharing the memory of a slice being filtered, kept so filtering the
+/// same slice again reuses the buffer and the image set on the filter.
+struct SharedSlice {
+    buffer: Buffer<u8>,
+    formats: &'static [Format],
+}
+
+impl SharedSlice {
+    fn new<T: BufferElement>(buffer: Buffer<T>) -> Self {
+        Self {
+            buffer: buffer.into_bytes(),
+            formats: T::FORMATS,
+        }
+    }
+
+    /// Takes the buffer in `slot` if it shares the `len` elements at `data`,
+    /// otherwise creates a new buffer sharing them.
+    ///
+    /// # Safety
+    /// `data` must be valid for `len` elements while the buffer is used, see
+    /// [Device::shared_buffer_from_raw]
+    unsafe fn take<T: BufferElement>(
+        slot: &mut Option<SharedSlice>,
+        device: &Device,
+        data: *mut T,
+        len: usize,
+    ) -> Result<Buffer<T>, OidnError> {
+        match slot.take() {
+            Some(shared)
+                if shared.formats == T::FORMATS
+                    && shared.buffer.byte_size() == mem::size_of::<T>() * len
+                    && oidnGetBufferData(shared.buffer.buf) == data as *mut _ =>
+            {
+                Ok(Buffer::from_bytes(shared.buffer))
+            }
+            _ => device.shared_buffer_from_raw(data, len),
         }
     }
 }
 
-unsafe impl<'a> Send for RayTracing<'a> {}
+/// The buffer and layout an image of a filter is set to, so setting the same
+/// image again can be skipped.
+#[derive(Default)]
+struct BoundImage(Option<(OIDNBuffer, ImageDesc)>);
+
+impl BoundImage {
+    /// Sets the image on the filter unless it is set to the same buffer and
+    /// layout already, returns whether the image changed.
+    ///
+    /// The filter keeps a reference to the buffer while it is set, so another
+    /// buffer can't reuse its handle.
+    ///
+    /// # Safety
+    /// `name` must be nul terminated
+    unsafe fn set<T: BufferElement>(
+        &mut self,
+        filter: &mut Filter,
+        name: &[u8],
+        buffer: &Buffer<T>,
+        desc: &ImageDesc,
+    ) -> Result<bool, OidnError> {
+        let image = Some((buffer.buf, *desc));
+        if self.0 == image {
+            return Ok(false);
+        }
+        filter.bind_image(name, buffer, desc)?;
+        self.0 = image;
+        Ok(true)
+    }
+
+    /// Unsets the image on the filter if it is set, returns whether the image
+    /// changed.
+    ///
+    /// # Safety
+    /// `name` must be nul terminated
+    unsafe fn unset(&mut self, filter: &mut Filter, name: &[u8]) -> bool {
+        if self.0.take().is_none() {
+            return false;
+        }
+        filter.unbind_image(name);
+        true
+    }
+}
@@ -0,0 +1,267 @@
+use crate::{
+    buffer::{output_buffer, Buffer, BufferElement, OutputBuffer},
+    error::{OidnError, Operation},
+    Error, Format,
+};
+
+/// Describes the layout of an image stored in a buffer.
+///
+/// By default the pixels are tightly packed starting at the beginning of the
+/// buffer. Setting an offset and strides allows using images interleaved with
+/// other data, e.g. the RGB channels of an RGBA framebuffer with padded rows:
+///
+/// ```
+/// # use oidn::{image::ImageDesc, Format};
+/// let desc = ImageDesc {
+///     pixel_byte_stride: 4 * 4,
+///     row_byte_stride: 1024 * 4 * 4,
+///     ..ImageDesc::new(1000, 800, Format::Float3)
+/// };
+/// assert_eq!(desc.required_byte_size(), Ok(799 * 1024 * 16 + 999 * 16 + 12));
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct ImageDesc {
+    pub width: usize,
+    pub height: usize,
+    pub format: Format,
+    /// Offset of the first pixel from the start of the buffer in bytes
+    pub byte_offset: usize,
+    /// Stride between pixels in bytes, 0 if the pixels are tightly packed
+    pub pixel_byte_stride: usize,
+    /// Stride between rows in bytes, 0 if the rows are tightly packed
+    pub row_byte_stride: usize,
+}
+
+impl ImageDesc {
+    /// Describes a tightly packed image at the start of a buffer
+    pub fn new(width: usize, height: usize, format: Format) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            byte_offset: 0,
+            pixel_byte_stride: 0,
+            row_byte_stride: 0,
+        }
+    }
+
+    /// The stride between pixels in bytes, taking packed pixels into account
+    pub fn effective_pixel_byte_stride(&self) -> usize {
+        if self.pixel_byte_stride == 0 {
+            self.format.byte_size()
+        } else {
+            self.pixel_byte_stride
+        }
+    }
+
+    /// The stride between rows in bytes, taking packed rows into account.
+    ///
+    /// Fails with [Error::InvalidImageDimensions] if the stride overflows.
+    pub fn effective_row_byte_stride(&self) -> Result<usize, Error> {
+        if self.row_byte_stride == 0 {
+            self.width
+                .checked_mul(self.effective_pixel_byte_stride())
+                .ok_or(Error::InvalidImageDimensions)
+        } else {
+            Ok(self.row_byte_stride)
+        }
+    }
+
+    /// The minimum size in bytes of a buffer holding the image.
+    ///
+    /// Fails with [Error::InvalidImageDimensions] if the size overflows.
+    pub fn required_byte_size(&self) -> Result<usize, Error> {
+        if self.width == 0 || self.height == 0 {
+            return Ok(self.byte_offset);
+        }
+        (self.height - 1)
+            .checked_mul(self.effective_row_byte_stride()?)
+            .and_then(|rows| {
+                let pixels = (self.width - 1).checked_mul(self.effective_pixel_byte_stride())?;
+                rows.checked_add(pixels)
+            })
+            .and_then(|size| size.checked_add(self.format.byte_size()))
+            .and_then(|size| size.checked_add(self.byte_offset))
+            .ok_or(Error::InvalidImageDimensions)
+    }
+
+    /// Whether the pixels of the image cover every byte of a buffer of
+    /// `byte_size` bytes
+    pub(crate) fn covers(&self, byte_size: usize) -> bool {
+        self.byte_offset == 0
+            && self.effective_pixel_byte_stride() == self.format.byte_size()
+            && self.effective_row_byte_stride().ok()
+                == self.width.checked_mul(self.format.byte_size())
+            && self.required_byte_size() == Ok(byte_size)
+    }
+
+    /// Checks the strides are large enough for the pixels not to overlap and
+    /// that the image fits in a buffer of `byte_size` bytes.
+    pub fn validate(&self, byte_size: usize) -> Result<(), Error> {
+        let packed_row = self
+            .width
+            .checked_mul(self.effective_pixel_byte_stride())
+            .ok_or(Error::InvalidImageDimensions)?;
+        if self.effective_pixel_byte_stride() < self.format.byte_size()
+            || self.effective_row_byte_stride()? < packed_row
+            || self.required_byte_size()? > byte_size
+        {
+            return Err(Error::InvalidImageDimensions);
+        }
+        Ok(())
+    }
+}
+
+/// An image stored in a [Buffer], with the layout of its pixels.
+///
+/// The layout is checked against the buffer when the view is created, so the
+/// image can be set on a [crate::Filter] as is. The buffer is only borrowed
+/// immutably, see [ImageViewMut] for images written by a filter.
+#[derive(Clone, Copy)]
+pub struct ImageView<'a, T: BufferElement = f32> {
+    buffer: &'a Buffer<T>,
+    desc: ImageDesc,
+}
+
+impl<'a, T: BufferElement> ImageView<'a, T> {
+    /// Creates a view of the image in the buffer, fails if the format does not
+    /// match the element type of the buffer or the image does not fit in it.
+    pub fn new(buffer: &'a Buffer<T>, desc: ImageDesc) -> Result<Self, OidnError> {
+        check_image(buffer, &desc)?;
+        Ok(Self { buffer, desc })
+    }
+
+    pub fn buffer(&self) -> &'a Buffer<T> {
+        self.buffer
+    }
+
+    pub fn desc(&self) -> &ImageDesc {
+        &self.desc
+    }
+}
+
+/// An image stored in a [Buffer] or [SharedBuffer](crate::buffer::SharedBuffer)
+/// which a filter can write to, e.g. its output.
+///
+/// The buffer stays mutably borrowed for as long as the view and the filter it
+/// is set on are used, so it can't be accessed while the filter writes to it.
+pub struct ImageViewMut<'a, T: BufferElement = f32> {
+    buffer: &'a mut Buffer<T>,
+    desc: ImageDesc,
+}
+
+impl<'a, T: BufferElement> ImageViewMut<'a, T> {
+    /// Creates a view of the image in the buffer, fails the same as
+    /// [ImageView::new].
+    pub fn new(buffer: &'a mut impl OutputBuffer<T>, desc: ImageDesc) -> Result<Self, OidnError> {
+        let buffer = output_buffer(buffer);
+        check_image(buffer, &desc)?;
+        Ok(Self { buffer, desc })
+    }
+
+    pub fn buffer(&self) -> &Buffer<T> {
+        self.buffer
+    }
+
+    pub fn desc(&self) -> &ImageDesc {
+        &self.desc
+    }
+}
+
+/// Checks the format of the image matches the element type of the buffer and
+/// the image fits in it
+fn check_image<T: BufferElement>(buffer: &Buffer<T>, desc: &ImageDesc) -> Result<(), OidnError> {
+    if !T::FORMATS.contains(&desc.format) {
+        return Err(OidnError::new(
+            Error::InvalidArgument,
+            Operation::ImageSetup,
+            format!(
+                "image format {:?} does not match the buffer element type",
+                desc.format
+            ),
+        ));
+    }
+    desc.validate(buffer.byte_size()).map_err(|code| {
+        OidnError::new(
+            code,
+            Operation::ImageSetup,
+            "image does not fit in its buffer or its pixels overlap",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_image_fits_exactly() {
+        let desc = ImageDesc::new(4, 2, Format::Float3);
+        assert_eq!(desc.required_byte_size(), Ok(4 * 2 * 12));
+        assert_eq!(desc.validate(4 * 2 * 12), Ok(()));
+        assert_eq!(
+            desc.validate(4 * 2 * 12 - 1),
+            Err(Error::InvalidImageDimensions)
+        );
+    }
+
+    #[test]
+    fn offset_must_fit_in_buffer() {
+        let desc = ImageDesc {
+            byte_offset: 16,
+            ..ImageDesc::new(4, 2, Format::Float3)
+        };
+        assert_eq!(desc.validate(16 + 4 * 2 * 12), Ok(()));
+        assert_eq!(
+            desc.validate(4 * 2 * 12),
+            Err(Error::InvalidImageDimensions)
+        );
+    }
+
+    #[test]
+    fn overlapping_pixels_are_rejected() {
+        let desc = ImageDesc {
+            pixel_byte_stride: 8,
+            ..ImageDesc::new(4, 2, Format::Float3)
+        };
+        assert_eq!(desc.validate(1024), Err(Error::InvalidImageDimensions));
+    }
+
+    #[test]
+    fn overlapping_rows_are_rejected() {
+        let desc = ImageDesc {
+            pixel_byte_stride: 16,
+            row_byte_stride: 3 * 16,
+            ..ImageDesc::new(4, 2, Format::Float3)
+        };
+        assert_eq!(desc.validate(1024), Err(Error::InvalidImageDimensions));
+    }
+
+    #[test]
+    fn strided_image_ends_at_last_pixel() {
+        let desc = ImageDesc {
+            pixel_byte_stride: 16,
+            row_byte_stride: 5 * 16,
+            ..ImageDesc::new(4, 2, Format::Float3)
+        };
+        let size = 5 * 16 + 3 * 16 + 12;
+        assert_eq!(desc.validate(size), Ok(()));
+        assert_eq!(desc.validate(size - 1), Err(Error::InvalidImageDimensions));
+    }
+
+    #[test]
+    fn overflowing_sizes_are_rejected() {
+        let desc = ImageDesc::new(1 << 62, 1, Format::Float);
+        assert_eq!(desc.validate(16), Err(Error::InvalidImageDimensions));
+        let desc = ImageDesc::new(usize::MAX, 2, Format::Float);
+        assert_eq!(desc.validate(16), Err(Error::InvalidImageDimensions));
+        let desc = ImageDesc {
+            byte_offset: usize::MAX,
+            ..ImageDesc::new(1, 1, Format::Float)
+        };
+        assert_eq!(
+            desc.required_byte_size(),
+            Err(Error::InvalidImageDimensions)
+        );
+    }
+}
@@ -17,46 +17,107 @@
 //! let mut filter_output = vec![0.0f32; input_img.len()];
 //!
 //! let device = oidn::Device::new();
-//! oidn::RayTracing::new(&device)
+//! let result = oidn::RayTracing::new(&device)
 //!     // Optionally add float3 normal and albedo buffers as well.
 //!     .srgb(true)
-//!     .image_dimensions(input.width() as usize, input.height() as usize);
-//!     .filter(&input_img[..], &mut filter_output[..])
-//!     .expect("Filter config error!");
+//!     .image_dimensions(input.width() as usize, input.height() as usize)
+//!     .filter(&input_img[..], &mut filter_output[..]);
 //!
-//! if let Err(e) = device.get_error() {
-//!     println!("Error denosing image: {}", e.1);
+//! if let Err(e) = result {
+//!     println!("Error denosing image: {e}");
 //! }
 //!
 //! // Save out or display filter_output image.
 //! ```
 
-use num_enum::TryFromPrimitive;
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr, BitOrAssign},
+};
+
+use num_enum::{FromPrimitive, TryFromPrimitive};
 
 pub mod buffer;
 pub mod device;
+pub mod error;
 pub mod filter;
+pub mod image;
+pub mod lightmap;
+pub mod physical_device;
+pub mod progress;
 #[allow(non_upper_case_globals, non_camel_case_types, non_snake_case)]
 pub mod sys;
 
 #[doc(inline)]
-pub use device::Device;
+pub use device::{Device, DeviceBuilder, DeviceInfo, Pending};
+#[doc(inline)]
+pub use error::{OidnError, Operation};
+#[doc(inline)]
+pub use filter::{Filter, RayTracing};
+#[doc(inline)]
+pub use image::{ImageDesc, ImageView, ImageViewMut};
+#[doc(inline)]
+pub use lightmap::RayTracingLightmap;
+#[doc(inline)]
+pub use physical_device::{physical_devices, PciAddress, PhysicalDevice};
 #[doc(inline)]
-pub use filter::RayTracing;
+pub use progress::CancellationToken;
 
+/// An error code, either reported by Open Image Denoise or detected by the
+/// bindings.
+///
+/// The codes reported by Open Image Denoise mirror `OIDNError`, codes which are
+/// not known to this version of the bindings are kept as [Error::Other]. Errors
+/// detected by the bindings use a separate range of codes starting at
+/// [Error::BINDINGS_CODE_BASE], which Open Image Denoise does not report.
 #[repr(u32)]
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, TryFromPrimitive)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, FromPrimitive)]
 pub enum Error {
     None = sys::OIDNError_OIDN_ERROR_NONE,
     Unknown = sys::OIDNError_OIDN_ERROR_UNKNOWN,
     InvalidArgument = sys::OIDNError_OIDN_ERROR_INVALID_ARGUMENT,
     InvalidOperation = sys::OIDNError_OIDN_ERROR_INVALID_OPERATION,
     OutOfMemory = sys::OIDNError_OIDN_ERROR_OUT_OF_MEMORY,
-    UnsupportedFormat = sys::OIDNError_OIDN_ERROR_UNSUPPORTED_HARDWARE,
+    UnsupportedHardware = sys::OIDNError_OIDN_ERROR_UNSUPPORTED_HARDWARE,
     Canceled = sys::OIDNError_OIDN_ERROR_CANCELLED,
-    InvalidImageDimensions,
+    /// An image does not fit in its buffer or its layout is invalid, detected
+    /// by the bindings
+    InvalidImageDimensions = Error::BINDINGS_CODE_BASE,
+    /// An error code reported by Open Image Denoise which is not known to this
+    /// version of the bindings
+    #[num_enum(catch_all)]
+    Other(u32),
+}
+
+impl Error {
+    /// The first code of the errors detected by the bindings
+    pub const BINDINGS_CODE_BASE: u32 = 0x1_0000;
+
+    /// Whether the error was detected by the bindings instead of being
+    /// reported by Open Image Denoise
+    pub fn is_bindings_error(&self) -> bool {
+        matches!(self, Error::InvalidImageDimensions)
+    }
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::None => f.write_str("no error"),
+            Error::Unknown => f.write_str("unknown error"),
+            Error::InvalidArgument => f.write_str("invalid argument"),
+            Error::InvalidOperation => f.write_str("invalid operation"),
+            Error::OutOfMemory => f.write_str("out of memory"),
+            Error::UnsupportedHardware => f.write_str("unsupported hardware"),
+            Error::Canceled => f.write_str("operation canceled"),
+            Error::InvalidImageDimensions => f.write_str("invalid image dimensions"),
+            Error::Other(code) => write!(f, "unknown error code {code}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, TryFromPrimitive)]
 pub enum Quality {
@@ -74,3 +135,217 @@ impl Quality {
         }
     }
 }
+
+/// Where the memory of a [buffer::Buffer] is stored.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, TryFromPrimitive)]
+pub enum Storage {
+    /// Pinned host memory, accessible by the host and the device
+    Host = sys::OIDNStorage_OIDN_STORAGE_HOST,
+    /// Device memory, only accessible by the device
+    Device = sys::OIDNStorage_OIDN_STORAGE_DEVICE,
+    /// Memory automatically migrated between the host and the device, only
+    /// available if [DeviceInfo::managed_memory_supported] is set
+    Managed = sys::OIDNStorage_OIDN_STORAGE_MANAGED,
+}
+
+impl Storage {
+    pub fn as_raw_oidn_storage(&self) -> sys::OIDNStorage {
+        *self as sys::OIDNStorage
+    }
+
+    /// Whether the memory can be accessed directly by the host
+    pub fn host_accessible(&self) -> bool {
+        matches!(self, Storage::Host | Storage::Managed)
+    }
+}
+
+/// The pixel format of an image.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, TryFromPrimitive)]
+pub enum Format {
+    Float = sys::OIDNFormat_OIDN_FORMAT_FLOAT,
+    Float2 = sys::OIDNFormat_OIDN_FORMAT_FLOAT2,
+    Float3 = sys::OIDNFormat_OIDN_FORMAT_FLOAT3,
+    Float4 = sys::OIDNFormat_OIDN_FORMAT_FLOAT4,
+    Half = sys::OIDNFormat_OIDN_FORMAT_HALF,
+    Half2 = sys::OIDNFormat_OIDN_FORMAT_HALF2,
+    Half3 = sys::OIDNFormat_OIDN_FORMAT_HALF3,
+    Half4 = sys::OIDNFormat_OIDN_FORMAT_HALF4,
+}
+
+impl Format {
+    pub fn as_raw_oidn_format(&self) -> sys::OIDNFormat {
+        *self as sys::OIDNFormat
+    }
+
+    /// The number of channels per pixel
+    pub fn channels(&self) -> usize {
+        match self {
+            Format::Float | Format::Half => 1,
+            Format::Float2 | Format::Half2 => 2,
+            Format::Float3 | Format::Half3 => 3,
+            Format::Float4 | Format::Half4 => 4,
+        }
+    }
+
+    /// The size of a pixel in bytes
+    pub fn byte_size(&self) -> usize {
+        match self {
+            Format::Float | Format::Float2 | Format::Float3 | Format::Float4 => self.channels() * 4,
+            Format::Half | Format::Half2 | Format::Half3 | Format::Half4 => self.channels() * 2,
+        }
+    }
+}
+
+/// The type of an Open Image Denoise device.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, TryFromPrimitive)]
+pub enum DeviceType {
+    /// Let Open Image Denoise pick the fastest device available.
+    Default = sys::OIDNDeviceType_OIDN_DEVICE_TYPE_DEFAULT,
+    Cpu = sys::OIDNDeviceType_OIDN_DEVICE_TYPE_CPU,
+    Sycl = sys::OIDNDeviceType_OIDN_DEVICE_TYPE_SYCL,
+    Cuda = sys::OIDNDeviceType_OIDN_DEVICE_TYPE_CUDA,
+    Hip = sys::OIDNDeviceType_OIDN_DEVICE_TYPE_HIP,
+    Metal = sys::OIDNDeviceType_OIDN_DEVICE_TYPE_METAL,
+}
+
+impl DeviceType {
+    pub fn as_raw_oidn_device_type(&self) -> sys::OIDNDeviceType {
+        *self as sys::OIDNDeviceType
+    }
+}
+
+/// A set of external memory types, see [DeviceInfo::external_memory_types].
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct ExternalMemoryTypeFlags(u32);
+
+impl ExternalMemoryTypeFlags {
+    pub const NONE: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_NONE);
+    /// Opaque POSIX file descriptor handle
+    pub const OPAQUE_FD: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_OPAQUE_FD);
+    /// File descriptor handle for a Linux dma_buf
+    pub const DMA_BUF: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_DMA_BUF);
+    /// NT handle
+    pub const OPAQUE_WIN32: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_OPAQUE_WIN32);
+    /// Global share (KMT) handle
+    pub const OPAQUE_WIN32_KMT: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_OPAQUE_WIN32_KMT);
+    /// NT handle returned by `IDXGIResource1::CreateSharedHandle` referring to
+    /// a Direct3D 11 texture resource
+    pub const D3D11_TEXTURE: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_D3D11_TEXTURE);
+    /// Global share (KMT) handle returned by `IDXGIResource::GetSharedHandle`
+    /// referring to a Direct3D 11 texture resource
+    pub const D3D11_TEXTURE_KMT: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_D3D11_TEXTURE_KMT);
+    /// NT handle returned by `IDXGIResource1::CreateSharedHandle` referring to
+    /// a Direct3D 11 resource
+    pub const D3D11_RESOURCE: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_D3D11_RESOURCE);
+    /// Global share (KMT) handle returned by `IDXGIResource::GetSharedHandle`
+    /// referring to a Direct3D 11 resource
+    pub const D3D11_RESOURCE_KMT: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_D3D11_RESOURCE_KMT);
+    /// NT handle returned by `ID3D12Device::CreateSharedHandle` referring to a
+    /// Direct3D 12 heap resource
+    pub const D3D12_HEAP: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_D3D12_HEAP);
+    /// NT handle returned by `ID3D12Device::CreateSharedHandle` referring to a
+    /// Direct3D 12 committed resource
+    pub const D3D12_RESOURCE: Self =
+        Self(sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_D3D12_RESOURCE);
+
+    pub const fn from_bits(bits: sys::OIDNExternalMemoryTypeFlag) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> sys::OIDNExternalMemoryTypeFlag {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns whether all the types in `other` are also in `self`
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// A type of external memory which can be imported from a POSIX file
+/// descriptor, see [Device::import_fd].
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, TryFromPrimitive)]
+pub enum ExternalMemoryType {
+    /// Opaque POSIX file descriptor handle
+    OpaqueFd = sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_OPAQUE_FD,
+    /// File descriptor handle for a Linux dma_buf
+    DmaBuf = sys::OIDNExternalMemoryTypeFlag_OIDN_EXTERNAL_MEMORY_TYPE_FLAG_DMA_BUF,
+}
+
+impl ExternalMemoryType {
+    pub fn as_raw_oidn_external_memory_type(&self) -> sys::OIDNExternalMemoryTypeFlag {
+        *self as sys::OIDNExternalMemoryTypeFlag
+    }
+}
+
+impl From<ExternalMemoryType> for ExternalMemoryTypeFlags {
+    fn from(memory_type: ExternalMemoryType) -> Self {
+        Self(memory_type.as_raw_oidn_external_memory_type())
+    }
+}
+
+impl BitOr for ExternalMemoryTypeFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for ExternalMemoryTypeFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for ExternalMemoryTypeFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_codes_round_trip() {
+        assert_eq!(Error::from(sys::OIDNError_OIDN_ERROR_NONE), Error::None);
+        assert_eq!(
+            Error::from(sys::OIDNError_OIDN_ERROR_CANCELLED),
+            Error::Canceled
+        );
+        assert_eq!(
+            Error::from(Error::BINDINGS_CODE_BASE),
+            Error::InvalidImageDimensions
+        );
+        assert!(Error::InvalidImageDimensions.is_bindings_error());
+    }
+
+    #[test]
+    fn unknown_error_codes_are_other() {
+        assert_eq!(Error::from(42), Error::Other(42));
+        assert!(!Error::Other(42).is_bindings_error());
+        assert_eq!(Error::Other(42).to_string(), "unknown error code 42");
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    buffer::{output_buffer, Buffer, BufferElement, OutputBuffer},
+    device::{Device, Pending},
+    error::OidnError,
+    filter::{Filter, FilterSetup, ImageFilter},
+    image::ImageDesc,
+    sys::*,
+    Format, Quality,
+};
+
+/// A ray tracing denoising filter for denoising lightmaps baked with Monte
+/// Carlo ray tracing methods.
+///
+/// The input lightmap must be HDR, either containing the irradiance (the
+/// default) or the directional coefficients (see
+/// [RayTracingLightmap::directional]).
+pub struct RayTracingLightmap {
+    inner: ImageFilter<LightmapSetup>,
+}
+
+/// The parameters of a [RayTracingLightmap] filter
+struct LightmapSetup {
+    directional: bool,
+    input_scale: f32,
+    max_memory_mb: i32,
+    filter_quality: OIDNQuality,
+}
+
+impl FilterSetup for LightmapSetup {
+    fn set_params(&self, filter: &mut Filter) {
+        filter
+            .set_bool("directional", self.directional)
+            .set_float("inputScale", self.input_scale)
+            .set_int("maxMemoryMB", self.max_memory_mb)
+            .set_int("quality", self.filter_quality as i32);
+    }
+}
+
+impl RayTracingLightmap {
+    /// Creates a new filter on the device, the filter keeps its own reference
+    /// to the device.
+    pub fn new(device: &Device) -> RayTracingLightmap {
+        let setup = LightmapSetup {
+            directional: false,
+            input_scale: f32::NAN,
+            max_memory_mb: -1,
+            filter_quality: 0,
+        };
+        RayTracingLightmap {
+            inner: unsafe { ImageFilter::new(device, b"RTLightmap\0", setup) },
+        }
+    }
+
+    /// Sets the quality of the output, the default is high.
+    ///
+    /// Balanced lowers the precision, if possible, however
+    /// some devices will not support this and so
+    /// the result (and performance) will stay the same as high.
+    pub fn filter_quality(&mut self, quality: Quality) -> &mut RayTracingLightmap {
+        self.inner.setup.filter_quality = quality.as_raw_oidn_quality();
+        self.inner.params_dirty = true;
+        self
+    }
+
+    /// Set whether the input contains normalized coefficients (in `[-1, 1]`)
+    /// of a directional lightmap (e.g. normalized L1 or higher spherical
+    /// harmonics band with the L0 band divided out) instead of irradiance.
+    pub fn directional(&mut self, directional: bool) -> &mut RayTracingLightmap {
+        self.inner.setup.directional = directional;
+        self.inner.params_dirty = true;
+        self
+    }
+
+    /// Sets a scale to apply to input values before filtering, without scaling
+    /// the output too.
+    ///
+    /// This can be used to map irradiance values to physical units, which
+    /// affects the quality of the output but not the range of the output
+    /// values. If not set, the scale is computed implicitly for irradiance
+    /// lightmaps or set to 1 for directional ones.
+    pub fn input_scale(&mut self, input_scale: f32) -> &mut RayTracingLightmap {
+        self.inner.setup.input_scale = input_scale;
+        self.inner.params_dirty = true;
+        self
+    }
+
+    /// Sets the approximate maximum scratch memory to use in megabytes (actual
+    /// memory usage may be higher), -1 (the default) limits it automatically.
+    pub fn max_memory_mb(&mut self, max_memory_mb: i32) -> &mut RayTracingLightmap {
+        self.inner.setup.max_memory_mb = max_memory_mb;
+        self.inner.params_dirty = true;
+        self
+    }
+
+    /// Sets the pixel format of the input lightmap, the default is
+    /// [Format::Float3].
+    pub fn color_format(&mut self, format: Format) -> &mut RayTracingLightmap {
+        self.inner.color_desc.format = format;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the pixel format of the output lightmap, the default is
+    /// [Format::Float3].
+    ///
+    /// When filtering in place the output format must be the same as the input
+    /// format.
+    pub fn output_format(&mut self, format: Format) -> &mut RayTracingLightmap {
+        self.inner.output_desc.format = format;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the layout of the input lightmap, including its dimensions.
+    pub fn color_desc(&mut self, desc: ImageDesc) -> &mut RayTracingLightmap {
+        self.inner.color_desc = desc;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the layout of the output lightmap, including its dimensions.
+    ///
+    /// When filtering in place the output layout must be the same as the input
+    /// layout.
+    pub fn output_desc(&mut self, desc: ImageDesc) -> &mut RayTracingLightmap {
+        self.inner.output_desc = desc;
+        self.inner.uncommitted = true;
+        self
+    }
+
+    /// Sets the dimensions of the input and output lightmaps.
+    pub fn image_dimensions(&mut self, width: usize, height: usize) -> &mut RayTracingLightmap {
+        self.inner.image_dimensions(width, height);
+        self
+    }
+
+    /// Filters the input lightmap into the output lightmap, sharing or copying
+    /// the slices the same as [RayTracing::filter](crate::RayTracing::filter).
+    pub fn filter<T: BufferElement>(
+        &mut self,
+        color: &[T],
+        output: &mut [T],
+    ) -> Result<(), OidnError> {
+        self.inner.filter_slices(Some(color), output)
+    }
+
+    pub fn filter_buffer<T: BufferElement>(
+        &mut self,
+        color: &Buffer<T>,
+        output: &mut impl OutputBuffer<T>,
+    ) -> Result<(), OidnError> {
+        self.inner.filter_buffer(Some(color), output_buffer(output))
+    }
+
+    pub fn filter_in_place<T: BufferElement>(&mut self, color: &mut [T]) -> Result<(), OidnError> {
+        self.inner.filter_slices(None, color)
+    }
+
+    pub fn filter_in_place_buffer<T: BufferElement>(
+        &mut self,
+        color: &mut impl OutputBuffer<T>,
+    ) -> Result<(), OidnError> {
+        self.inner.filter_buffer(None, output_buffer(color))
+    }
+
+    /// Starts filtering asynchronously, the same as
+    /// [RayTracingLightmap::filter_buffer] otherwise.
+    ///
+    /// # Safety
+    /// See [RayTracing::filter_buffer_async](crate::RayTracing::filter_buffer_async).
+    pub unsafe fn filter_buffer_async<'b, T: BufferElement>(
+        &'b mut self,
+        color: &'b Buffer<T>,
+        output: &'b mut impl OutputBuffer<T>,
+    ) -> Result<Pending<'b>, OidnError> {
+        self.inner
+            .filter_buffer_async(Some(color), output_buffer(output))
+    }
+
+    /// Starts filtering asynchronously, the same as
+    /// [RayTracingLightmap::filter_in_place_buffer] otherwise.
+    ///
+    /// # Safety
+    /// See [RayTracing::filter_buffer_async](crate::RayTracing::filter_buffer_async).
+    pub unsafe fn filter_in_place_buffer_async<'b, T: BufferElement>(
+        &'b mut self,
+        color: &'b mut impl OutputBuffer<T>,
+    ) -> Result<Pending<'b>, OidnError> {
+        self.inner.filter_buffer_async(None, output_buffer(color))
+    }
+}
+
+unsafe impl Send for RayTracingLightmap {}
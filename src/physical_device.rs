@@ -0,0 +1,202 @@
+use std::{ffi::CStr, os::raw::c_char};
+
+use crate::sys::*;
+use crate::DeviceType;
+
+/// A PCI address of a physical device.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct PciAddress {
+    pub domain: i32,
+    pub bus: i32,
+    pub device: i32,
+    pub function: i32,
+}
+
+/// A physical device (e.g. a CPU or a GPU) supported by Open Image Denoise.
+///
+/// The properties are queried once when the physical device is enumerated,
+/// identifiers which are not supported by the device are reported as [None].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhysicalDevice {
+    id: i32,
+    device_type: Option<DeviceType>,
+    name: String,
+    uuid: Option<[u8; 16]>,
+    luid: Option<[u8; 8]>,
+    node_mask: Option<u32>,
+    pci_address: Option<PciAddress>,
+}
+
+impl PhysicalDevice {
+    /// Queries the physical device with the given ID, returns [None] if there
+    /// is no such device.
+    pub fn get(id: i32) -> Option<Self> {
+        if id < 0 || id >= unsafe { oidnGetNumPhysicalDevices() } {
+            return None;
+        }
+        let (device_type, name, uuid, luid, node_mask, pci_address) = unsafe {
+            let uuid = if physical_device_bool(id, b"uuidSupported\0") {
+                physical_device_data(id, b"uuid\0")
+            } else {
+                None
+            };
+            let (luid, node_mask) = if physical_device_bool(id, b"luidSupported\0") {
+                (
+                    physical_device_data(id, b"luid\0"),
+                    Some(physical_device_int(id, b"nodeMask\0") as u32),
+                )
+            } else {
+                (None, None)
+            };
+            let pci_address = if physical_device_bool(id, b"pciAddressSupported\0") {
+                Some(PciAddress {
+                    domain: physical_device_int(id, b"pciDomain\0"),
+                    bus: physical_device_int(id, b"pciBus\0"),
+                    device: physical_device_int(id, b"pciDevice\0"),
+                    function: physical_device_int(id, b"pciFunction\0"),
+                })
+            } else {
+                None
+            };
+            (
+                physical_device_int(id, b"type\0"),
+                physical_device_string(id, b"name\0"),
+                uuid,
+                luid,
+                node_mask,
+                pci_address,
+            )
+        };
+        Some(Self {
+            id,
+            device_type: (device_type as u32).try_into().ok(),
+            name,
+            uuid,
+            luid,
+            node_mask,
+            pci_address,
+        })
+    }
+
+    /// The ID of the physical device, in the range `0..physical_devices().len()`
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// The type of the physical device, [None] if the type is not known to
+    /// this version of the bindings
+    pub fn device_type(&self) -> Option<DeviceType> {
+        self.device_type
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The universally unique identifier of the physical device, if supported
+    pub fn uuid(&self) -> Option<[u8; 16]> {
+        self.uuid
+    }
+
+    /// The locally unique identifier of the physical device, if supported
+    pub fn luid(&self) -> Option<[u8; 8]> {
+        self.luid
+    }
+
+    /// The bitfield identifying the node within a linked device adapter
+    /// corresponding to the device, supported if the LUID is supported
+    pub fn node_mask(&self) -> Option<u32> {
+        self.node_mask
+    }
+
+    /// The PCI address of the physical device, if supported
+    pub fn pci_address(&self) -> Option<PciAddress> {
+        self.pci_address
+    }
+
+    pub fn uuid_supported(&self) -> bool {
+        self.uuid.is_some()
+    }
+
+    pub fn luid_supported(&self) -> bool {
+        self.luid.is_some()
+    }
+
+    pub fn pci_address_supported(&self) -> bool {
+        self.pci_address.is_some()
+    }
+}
+
+/// # Safety
+/// `name` must be nul terminated
+unsafe fn physical_device_bool(id: i32, name: &[u8]) -> bool {
+    oidnGetPhysicalDeviceBool(id, name.as_ptr() as *const c_char)
+}
+
+/// # Safety
+/// `name` must be nul terminated
+unsafe fn physical_device_int(id: i32, name: &[u8]) -> i32 {
+    oidnGetPhysicalDeviceInt(id, name.as_ptr() as *const c_char)
+}
+
+/// # Safety
+/// `name` must be nul terminated
+unsafe fn physical_device_string(id: i32, name: &[u8]) -> String {
+    let value = oidnGetPhysicalDeviceString(id, name.as_ptr() as *const c_char);
+    if value.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(value).to_string_lossy().to_string()
+}
+
+/// # Safety
+/// `name` must be nul terminated
+unsafe fn physical_device_data<const N: usize>(id: i32, name: &[u8]) -> Option<[u8; N]> {
+    let mut byte_size = 0;
+    let data = oidnGetPhysicalDeviceData(id, name.as_ptr() as *const c_char, &mut byte_size);
+    if data.is_null() || byte_size != N {
+        return None;
+    }
+    let mut out = [0; N];
+    out.copy_from_slice(std::slice::from_raw_parts(data as *const u8, N));
+    Some(out)
+}
+
+/// An iterator over the physical devices supported by Open Image Denoise,
+/// created by [physical_devices].
+#[derive(Debug, Clone)]
+pub struct PhysicalDevices {
+    next: i32,
+    count: i32,
+}
+
+/// Enumerates the physical devices supported by Open Image Denoise.
+///
+/// The devices are returned in the order Open Image Denoise reports them,
+/// which is stable for a given machine and library version.
+pub fn physical_devices() -> PhysicalDevices {
+    PhysicalDevices {
+        next: 0,
+        count: unsafe { oidnGetNumPhysicalDevices() },
+    }
+}
+
+impl Iterator for PhysicalDevices {
+    type Item = PhysicalDevice;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.count {
+            return None;
+        }
+        let device = PhysicalDevice::get(self.next);
+        self.next += 1;
+        device
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.count - self.next).max(0) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for PhysicalDevices {}
@@ -0,0 +1,105 @@
+use std::{
+    os::raw::c_void,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// A token to cancel filtering from another thread, e.g. when the scene
+/// changes while a large image is being denoised.
+///
+/// Clones share the same cancellation state. Set the token as the progress
+/// monitor of a filter with [CancellationToken::monitor] (or
+/// [CancellationToken::monitor_with] to also report progress), filtering then
+/// returns [crate::Error::Canceled] once the token is canceled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels filtering with any monitor created from this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears the cancellation so the token can be used to filter again
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    /// A progress monitor which cancels filtering once the token is canceled
+    pub fn monitor(&self) -> impl FnMut(f64) -> bool + Send + 'static {
+        self.monitor_with(|_| true)
+    }
+
+    /// A progress monitor which calls `monitor` with the progress, cancelling
+    /// filtering once either the token is canceled or `monitor` returns false
+    pub fn monitor_with(
+        &self,
+        mut monitor: impl FnMut(f64) -> bool + Send + 'static,
+    ) -> impl FnMut(f64) -> bool + Send + 'static {
+        let token = self.clone();
+        move |progress| !token.is_canceled() && monitor(progress)
+    }
+}
+
+/// A progress monitor set on a filter, recording whether it canceled the
+/// filter.
+pub(crate) struct ProgressMonitor {
+    callback: Box<dyn FnMut(f64) -> bool + Send>,
+    canceled: bool,
+}
+
+impl ProgressMonitor {
+    pub(crate) fn new(callback: impl FnMut(f64) -> bool + Send + 'static) -> Box<Self> {
+        Box::new(Self {
+            callback: Box::new(callback),
+            canceled: false,
+        })
+    }
+
+    /// Returns whether the monitor canceled the filter since the last call,
+    /// resetting it for the next execution
+    pub(crate) fn take_canceled(&mut self) -> bool {
+        std::mem::take(&mut self.canceled)
+    }
+}
+
+/// # Safety
+/// `user_ptr` must point to a [ProgressMonitor] which is not accessed
+/// elsewhere while the filter executes
+pub(crate) unsafe extern "C" fn progress_monitor_trampoline(
+    user_ptr: *mut c_void,
+    progress: f64,
+) -> bool {
+    let monitor = &mut *(user_ptr as *mut ProgressMonitor);
+    let proceed = (monitor.callback)(progress);
+    if !proceed {
+        monitor.canceled = true;
+    }
+    proceed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canceled_token_stops_monitor_until_reset() {
+        let token = CancellationToken::new();
+        let mut monitor = token.monitor();
+        assert!(monitor(0.0));
+        token.clone().cancel();
+        assert!(!monitor(0.5));
+        token.reset();
+        assert!(monitor(1.0));
+    }
+}